@@ -1,6 +1,6 @@
 use proc_macro2::TokenStream;
 use quote::{quote, ToTokens};
-use syn::{Data, DeriveInput, Error, Expr, Field, Fields, LitInt, LitStr};
+use syn::{Data, DeriveInput, Error, Expr, Field, Fields, Ident, LitBool, LitInt, LitStr, Token};
 
 pub struct ParamAttr {
     pub id: LitInt,
@@ -9,8 +9,25 @@ pub struct ParamAttr {
     pub parse: Option<Expr>,
     pub display: Option<Expr>,
     pub format: Option<LitStr>,
+    pub group: Option<LitStr>,
+    pub unit: Option<LitStr>,
+    pub flags: Vec<(Ident, LitBool)>,
 }
 
+const FLAGS: &[&str] = &[
+    "automatable",
+    "hidden",
+    "read_only",
+    "bypass",
+    "list",
+    "requires_process",
+    "modulatable",
+    "modulatable_per_note_id",
+    "modulatable_per_key",
+    "modulatable_per_channel",
+    "modulatable_per_port",
+];
+
 pub fn parse_param(field: &Field) -> Result<Option<ParamAttr>, Error> {
     let mut is_param = false;
 
@@ -20,6 +37,9 @@ pub fn parse_param(field: &Field) -> Result<Option<ParamAttr>, Error> {
     let mut parse = None;
     let mut display = None;
     let mut format = None;
+    let mut group = None;
+    let mut unit = None;
+    let mut flags: Vec<(Ident, LitBool)> = Vec::new();
 
     for attr in &field.attrs {
         if !attr.path().is_ident("param") {
@@ -100,6 +120,40 @@ pub fn parse_param(field: &Field) -> Result<Option<ParamAttr>, Error> {
                 }
 
                 format = Some(meta.value()?.parse::<LitStr>()?);
+            } else if ident == "group" {
+                if group.is_some() {
+                    return Err(Error::new_spanned(
+                        &meta.path,
+                        "duplicate param attribute `group`",
+                    ));
+                }
+
+                group = Some(meta.value()?.parse::<LitStr>()?);
+            } else if ident == "unit" {
+                if unit.is_some() {
+                    return Err(Error::new_spanned(
+                        &meta.path,
+                        "duplicate param attribute `unit`",
+                    ));
+                }
+
+                unit = Some(meta.value()?.parse::<LitStr>()?);
+            } else if FLAGS.iter().any(|flag| ident == flag) {
+                if flags.iter().any(|(flag, _)| flag == ident) {
+                    return Err(Error::new_spanned(
+                        &meta.path,
+                        format!("duplicate param attribute `{}`", ident),
+                    ));
+                }
+
+                // Flags can be written either as `flag` or as `flag = <bool>`.
+                let value = if meta.input.peek(Token![=]) {
+                    meta.value()?.parse::<LitBool>()?
+                } else {
+                    LitBool::new(true, ident.span())
+                };
+
+                flags.push((ident.clone(), value));
             } else {
                 return Err(Error::new_spanned(
                     &meta.path,
@@ -128,6 +182,9 @@ pub fn parse_param(field: &Field) -> Result<Option<ParamAttr>, Error> {
         parse,
         display,
         format,
+        group,
+        unit,
+        flags,
     }))
 }
 
@@ -212,6 +269,36 @@ pub fn expand_params(input: &DeriveInput) -> Result<TokenStream, Error> {
             quote! { <#ty as ::coupler::params::Encode>::steps() }
         };
 
+        let (min, max, to_plain, from_plain) = if let Some(range) = &field.param.range {
+            (
+                quote! { ::coupler::params::Range::<#ty>::min_plain(&(#range)) },
+                quote! { ::coupler::params::Range::<#ty>::max_plain(&(#range)) },
+                quote! { ::coupler::params::Range::<#ty>::decode_plain(&(#range), __value) },
+                quote! { ::coupler::params::Range::<#ty>::encode_plain(&(#range), __value) },
+            )
+        } else {
+            (
+                quote! { <#ty as ::coupler::params::Encode>::min_plain() },
+                quote! { <#ty as ::coupler::params::Encode>::max_plain() },
+                quote! { <#ty as ::coupler::params::Encode>::decode_plain(__value) },
+                quote! { <#ty as ::coupler::params::Encode>::encode_plain(__value) },
+            )
+        };
+
+        let unit = if let Some(unit) = &field.param.unit {
+            unit.clone()
+        } else {
+            LitStr::new("", ident.span())
+        };
+
+        let group = if let Some(group) = &field.param.group {
+            quote! { ::std::option::Option::Some(::std::string::ToString::to_string(#group)) }
+        } else {
+            quote! { ::std::option::Option::None }
+        };
+
+        let flags = field.param.flags.iter().map(|(flag, value)| quote! { #flag: #value });
+
         let encode = gen_encode(&field.field, &field.param, quote! { __value });
         let parse = if let Some(parse) = &field.param.parse {
             quote! {
@@ -244,8 +331,18 @@ pub fn expand_params(input: &DeriveInput) -> Result<TokenStream, Error> {
                 name: ::std::string::ToString::to_string(#name),
                 default: #default,
                 steps: #steps,
+                flags: ::coupler::params::ParamFlags {
+                    #(#flags,)*
+                    ..::std::default::Default::default()
+                },
+                group: #group,
+                min: #min,
+                max: #max,
+                unit: ::std::string::ToString::to_string(#unit),
                 parse: ::std::boxed::Box::new(|__str| #parse),
                 display: ::std::boxed::Box::new(|__value, __formatter| #display),
+                to_plain: ::std::boxed::Box::new(|__value| #to_plain),
+                from_plain: ::std::boxed::Box::new(|__value| #from_plain),
             }
         }
     });
@@ -45,8 +45,12 @@ impl Plugin for Gain {
                     formats: vec![Format::Mono],
                 },
             ],
+            note_ports: Vec::new(),
             params: GainParams::params(),
+            bypass: None,
             has_editor: false,
+            transport: TransportFields::default(),
+            note_expressions: Vec::new(),
         }
     }
 
@@ -82,7 +86,7 @@ impl Plugin for Gain {
         }
     }
 
-    fn editor(&self, _parent: Parent) -> Self::Editor {
+    fn editor(&self, _context: EditorContext, _parent: Parent) -> Self::Editor {
         NoEditor
     }
 }
@@ -114,7 +118,13 @@ impl Processor for GainProcessor {
 
     fn reset(&mut self) {}
 
-    fn process(&mut self, buffers: Buffers, events: Events) {
+    fn process(
+        &mut self,
+        buffers: Buffers,
+        events: Events,
+        _output: &mut OutputEvents,
+        _transport: &Transport,
+    ) -> Status {
         let buffer: BufferMut = buffers.try_into().unwrap();
         for (buffer, events) in buffer.split_at_events(events) {
             for event in events {
@@ -132,5 +142,7 @@ impl Processor for GainProcessor {
                 }
             }
         }
+
+        Status::ContinueIfNotQuiet
     }
 }
@@ -1,3 +1,4 @@
+use std::cell::Cell;
 use std::marker::PhantomData;
 use std::ops::{Index, IndexMut, Range};
 use std::slice;
@@ -22,6 +23,14 @@ pub enum AnyBuffer<'a, 'b> {
 }
 
 impl<'a, 'b> AnyBuffer<'a, 'b> {
+    #[inline]
+    pub fn is_constant(&self, channel: usize) -> bool {
+        match self {
+            AnyBuffer::Const(buffer) => buffer.is_constant(channel),
+            AnyBuffer::Mut(buffer) => buffer.is_constant(channel),
+        }
+    }
+
     #[inline]
     pub unsafe fn from_raw_parts(
         buffer_type: BufferType,
@@ -39,6 +48,10 @@ pub struct BufferData {
     pub buffer_type: BufferType,
     pub start: usize,
     pub end: usize,
+    // Bitmask of input channels which the host has reported as constant for the current block.
+    pub input_constant: u64,
+    // Bitmask of output channels which the processor has marked as constant for the current block.
+    pub output_constant: Cell<u64>,
 }
 
 #[derive(Copy, Clone)]
@@ -82,6 +95,8 @@ impl<'a, 'b> Buffers<'a, 'b> {
                     buffer.buffer_type,
                     RawBuffer {
                         ptrs: &self.raw.ptrs[buffer.start..buffer.end],
+                        input_constant: buffer.input_constant,
+                        output_constant: &buffer.output_constant,
                         offset: self.raw.offset,
                     },
                     self.len,
@@ -158,6 +173,8 @@ impl<'a, 'b> Iterator for BufferIter<'a, 'b> {
                     buffer.buffer_type,
                     RawBuffer {
                         ptrs: &self.ptrs[buffer.start..buffer.end],
+                        input_constant: buffer.input_constant,
+                        output_constant: &buffer.output_constant,
                         offset: self.offset,
                     },
                     self.len,
@@ -203,6 +220,8 @@ impl<'a, 'b> BufferSamples<'a, 'b> {
                     buffer.buffer_type,
                     RawBuffer {
                         ptrs: &self.raw.ptrs[buffer.start..buffer.end],
+                        input_constant: buffer.input_constant,
+                        output_constant: &buffer.output_constant,
                         offset: self.raw.offset,
                     },
                 ))
@@ -246,6 +265,8 @@ impl<'a, 'b> Iterator for BufferSampleIter<'a, 'b> {
                     buffer.buffer_type,
                     RawBuffer {
                         ptrs: &self.ptrs[buffer.start..buffer.end],
+                        input_constant: buffer.input_constant,
+                        output_constant: &buffer.output_constant,
                         offset: self.offset,
                     },
                 ))
@@ -259,6 +280,8 @@ impl<'a, 'b> Iterator for BufferSampleIter<'a, 'b> {
 #[derive(Copy, Clone)]
 pub struct RawBuffer<'a> {
     pub ptrs: &'a [*mut f32],
+    pub input_constant: u64,
+    pub output_constant: &'a Cell<u64>,
     pub offset: isize,
 }
 
@@ -279,6 +302,15 @@ impl<'a, 'b> Buffer<'a, 'b> {
     pub fn channel_count(&self) -> usize {
         self.raw.ptrs.len()
     }
+
+    // Returns whether the host has reported that a channel holds the same value for every sample
+    // of the block.
+    #[inline]
+    pub fn is_constant(&self, channel: usize) -> bool {
+        channel < self.channel_count()
+            && channel < 64
+            && self.raw.input_constant & (1 << channel) != 0
+    }
 }
 
 impl<'a, 'b> Index<usize> for Buffer<'a, 'b> {
@@ -396,6 +428,15 @@ impl<'a, 'b> BufferMut<'a, 'b> {
         self.raw.ptrs.len()
     }
 
+    // Returns whether the host has reported that a channel of the input to this buffer holds the
+    // same value for every sample of the block. Not affected by `set_constant`.
+    #[inline]
+    pub fn is_constant(&self, channel: usize) -> bool {
+        channel < self.channel_count()
+            && channel < 64
+            && self.raw.input_constant & (1 << channel) != 0
+    }
+
     #[inline]
     pub fn reborrow<'c>(&'c mut self) -> BufferMut<'a, 'c> {
         BufferMut {
@@ -404,6 +445,17 @@ impl<'a, 'b> BufferMut<'a, 'b> {
             _marker: self._marker,
         }
     }
+
+    // Marks a channel as holding the same value for every sample, allowing the host to skip
+    // processing it. The flag applies to the entire block, even when set through a sub-buffer.
+    #[inline]
+    pub fn set_constant(&mut self, channel: usize, constant: bool) {
+        if channel < self.channel_count() && channel < 64 {
+            let mask = self.raw.output_constant.get();
+            let bit = 1 << channel;
+            self.raw.output_constant.set(if constant { mask | bit } else { mask & !bit });
+        }
+    }
 }
 
 impl<'a, 'b> Index<usize> for BufferMut<'a, 'b> {
@@ -10,6 +10,20 @@ pub struct BusInfo {
     pub dir: BusDir,
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum NoteDialect {
+    Clap,
+    Midi,
+    Midi2,
+}
+
+pub struct NotePortInfo {
+    pub name: String,
+    pub dir: BusDir,
+    pub dialects: Vec<NoteDialect>,
+    pub preferred_dialect: NoteDialect,
+}
+
 #[derive(Clone, Eq, PartialEq, Hash)]
 pub struct Layout {
     pub formats: Vec<Format>,
@@ -0,0 +1,451 @@
+use std::iter::zip;
+
+use crate::buffers::{AnyBuffer, Buffers};
+use crate::bus::BusDir;
+use crate::events::{Data, Events, OutputEvents};
+use crate::params::{ParamId, ParamValue};
+use crate::plugin::{Plugin, PluginInfo};
+use crate::process::{Config, Processor, Status, Transport};
+
+const CROSSFADE_SECONDS: f64 = 0.01;
+
+struct DryChannel {
+    delay: Vec<f32>,
+    dry: Vec<f32>,
+}
+
+// Wraps the plugin's processor to implement the bypass param designated by `PluginInfo::bypass`.
+// The input of each in-out bus is delayed by the plugin's latency and crossfaded with the
+// processed output, and the processor is skipped entirely once the crossfade has finished.
+pub struct Bypass<T> {
+    processor: T,
+    param: Option<ParamId>,
+    dirs: Vec<BusDir>,
+    channels: Vec<Vec<DryChannel>>,
+    latency: usize,
+    pos: usize,
+    bypassed: bool,
+    // Whether the processor was skipped in the previous block.
+    skipped: bool,
+    mix: f32,
+    step: f32,
+}
+
+impl<T: Processor> Bypass<T> {
+    pub fn new<P>(plugin: &P, info: &PluginInfo, config: Config) -> Bypass<T>
+    where
+        P: Plugin<Processor = T>,
+    {
+        let param = info.bypass;
+        let bypassed = param.is_some_and(|id| plugin.get_param(id) >= 0.5);
+
+        // Allocate all of the scratch space up front, since it is used on the audio thread.
+        let latency = plugin.latency(&config) as usize;
+        let mut channels = Vec::new();
+        if param.is_some() {
+            for (bus, format) in zip(&info.buses, &config.layout.formats) {
+                let channel_count = if bus.dir == BusDir::InOut {
+                    format.channel_count()
+                } else {
+                    0
+                };
+
+                let bus_channels = (0..channel_count)
+                    .map(|_| DryChannel {
+                        delay: vec![0.0; latency],
+                        dry: vec![0.0; config.max_buffer_size],
+                    })
+                    .collect();
+                channels.push(bus_channels);
+            }
+        }
+
+        let crossfade_len = (CROSSFADE_SECONDS * config.sample_rate).max(1.0);
+
+        Bypass {
+            dirs: info.buses.iter().map(|bus| bus.dir).collect(),
+            processor: plugin.processor(config),
+            param,
+            channels,
+            latency,
+            pos: 0,
+            bypassed,
+            skipped: false,
+            mix: if bypassed { 1.0 } else { 0.0 },
+            step: (1.0 / crossfade_len) as f32,
+        }
+    }
+
+    fn target(&self) -> f32 {
+        if self.bypassed {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+impl<T: Processor> Processor for Bypass<T> {
+    fn set_param(&mut self, id: ParamId, value: ParamValue) {
+        if self.param == Some(id) {
+            self.bypassed = value >= 0.5;
+        }
+
+        self.processor.set_param(id, value);
+    }
+
+    fn reset(&mut self) {
+        self.processor.reset();
+
+        for bus_channels in &mut self.channels {
+            for channel in bus_channels {
+                channel.delay.fill(0.0);
+            }
+        }
+        self.pos = 0;
+        self.mix = self.target();
+    }
+
+    fn process(
+        &mut self,
+        mut buffers: Buffers,
+        events: Events,
+        output: &mut OutputEvents,
+        transport: &Transport,
+    ) -> Status {
+        let Some(param) = self.param else {
+            return self.processor.process(buffers, events, output, transport);
+        };
+
+        // Bypass changes take effect at block granularity; the crossfade hides the difference.
+        for event in events {
+            if let Data::ParamChange { id, value } = event.data {
+                if id == param {
+                    self.bypassed = value >= 0.5;
+                }
+            }
+        }
+
+        let len = buffers.len();
+
+        // Save the delayed dry signal before the processor overwrites the in-out buffers.
+        let pos = self.pos;
+        let latency = self.latency;
+        for (bus_channels, buffer) in zip(&mut self.channels, buffers.reborrow()) {
+            let AnyBuffer::Mut(buffer) = buffer else {
+                continue;
+            };
+
+            for (index, channel) in bus_channels.iter_mut().enumerate() {
+                let input = &buffer[index];
+                if latency == 0 {
+                    channel.dry[..len].copy_from_slice(input);
+                    continue;
+                }
+
+                for (i, &sample) in input.iter().enumerate() {
+                    let delay_pos = (pos + i) % latency;
+                    channel.dry[i] = channel.delay[delay_pos];
+                    channel.delay[delay_pos] = sample;
+                }
+            }
+        }
+        if latency > 0 {
+            self.pos = (pos + len) % latency;
+        }
+
+        let start = self.mix;
+        let target = self.target();
+        let skip = start == 1.0 && target == 1.0;
+
+        let status = if skip {
+            // Keep the processor's params up to date even while it isn't running.
+            for event in events {
+                if let Data::ParamChange { id, value } = event.data {
+                    self.processor.set_param(id, value);
+                }
+            }
+
+            Status::Continue
+        } else {
+            // Anything the processor buffered before it was skipped is stale by now.
+            if self.skipped {
+                self.processor.reset();
+            }
+
+            self.processor.process(buffers.reborrow(), events, output, transport)
+        };
+        self.skipped = skip;
+
+        if start == 0.0 && target == 0.0 {
+            return status;
+        }
+
+        let step = if target > start {
+            self.step
+        } else if target < start {
+            -self.step
+        } else {
+            0.0
+        };
+        let mix_at = |i: usize| (start + step * (i + 1) as f32).clamp(0.0, 1.0);
+
+        for ((dir, bus_channels), buffer) in
+            zip(zip(&self.dirs, &self.channels), buffers.reborrow())
+        {
+            let AnyBuffer::Mut(mut buffer) = buffer else {
+                continue;
+            };
+
+            match dir {
+                BusDir::InOut => {
+                    for (index, channel) in bus_channels.iter().enumerate() {
+                        for (i, sample) in buffer[index].iter_mut().enumerate() {
+                            let mix = mix_at(i);
+                            *sample = *sample * (1.0 - mix) + channel.dry[i] * mix;
+                        }
+                        buffer.set_constant(index, false);
+                    }
+                }
+                BusDir::Out => {
+                    for index in 0..buffer.channel_count() {
+                        if skip {
+                            buffer[index].fill(0.0);
+                            buffer.set_constant(index, true);
+                        } else {
+                            for (i, sample) in buffer[index].iter_mut().enumerate() {
+                                *sample *= 1.0 - mix_at(i);
+                            }
+                            buffer.set_constant(index, false);
+                        }
+                    }
+                }
+                BusDir::In => {}
+            }
+        }
+
+        self.mix = (start + step * len as f32).clamp(0.0, 1.0);
+
+        Status::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::collections::VecDeque;
+    use std::io::{self, Read, Write};
+
+    use super::*;
+    use crate::buffers::{BufferData, BufferType, BufferView, RawBuffers};
+    use crate::bus::{BusInfo, Format, Layout};
+    use crate::editor::{EditorContext, NoEditor, Parent};
+    use crate::events::{Event, EventQueue};
+    use crate::plugin::Host;
+
+    const BYPASS: ParamId = 0;
+
+    struct TestPlugin {
+        latency: usize,
+        bypassed: bool,
+    }
+
+    impl Plugin for TestPlugin {
+        type Processor = TestProcessor;
+        type Editor = NoEditor;
+
+        fn info() -> PluginInfo {
+            PluginInfo::default()
+        }
+
+        fn new(_host: Host) -> Self {
+            TestPlugin {
+                latency: 0,
+                bypassed: false,
+            }
+        }
+
+        fn set_param(&mut self, _id: ParamId, _value: ParamValue) {}
+
+        fn get_param(&self, _id: ParamId) -> ParamValue {
+            if self.bypassed {
+                1.0
+            } else {
+                0.0
+            }
+        }
+
+        fn save(&self, _output: &mut impl Write) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn load(&mut self, _input: &mut impl Read) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn processor(&self, _config: Config) -> Self::Processor {
+            TestProcessor {
+                delay: vec![0.0; self.latency].into(),
+                resets: 0,
+            }
+        }
+
+        fn editor(&self, _context: EditorContext, _parent: Parent) -> Self::Editor {
+            NoEditor
+        }
+
+        fn latency(&self, _config: &Config) -> u64 {
+            self.latency as u64
+        }
+    }
+
+    // Doubles its input and delays it by the plugin's latency.
+    struct TestProcessor {
+        delay: VecDeque<f32>,
+        resets: usize,
+    }
+
+    impl Processor for TestProcessor {
+        fn set_param(&mut self, _id: ParamId, _value: ParamValue) {}
+
+        fn reset(&mut self) {
+            self.delay.iter_mut().for_each(|sample| *sample = 0.0);
+            self.resets += 1;
+        }
+
+        fn process(
+            &mut self,
+            buffers: Buffers,
+            _events: Events,
+            _output: &mut OutputEvents,
+            _transport: &Transport,
+        ) -> Status {
+            for buffer in buffers {
+                let AnyBuffer::Mut(mut buffer) = buffer else {
+                    continue;
+                };
+
+                for sample in buffer[0].iter_mut() {
+                    self.delay.push_back(2.0 * *sample);
+                    *sample = self.delay.pop_front().unwrap();
+                }
+            }
+
+            Status::Continue
+        }
+    }
+
+    // The crossfade is 8 samples long at this sample rate.
+    fn bypass(latency: usize, bypassed: bool) -> Bypass<TestProcessor> {
+        let plugin = TestPlugin { latency, bypassed };
+        let info = PluginInfo {
+            buses: vec![BusInfo {
+                name: "Main".to_string(),
+                dir: BusDir::InOut,
+            }],
+            bypass: Some(BYPASS),
+            ..PluginInfo::default()
+        };
+        let config = Config {
+            layout: Layout {
+                formats: vec![Format::Mono],
+            },
+            sample_rate: 800.0,
+            max_buffer_size: 16,
+        };
+
+        Bypass::new(&plugin, &info, config)
+    }
+
+    fn run(bypass: &mut Bypass<TestProcessor>, input: &[f32], bypassed: Option<bool>) -> Vec<f32> {
+        let mut samples = input.to_vec();
+
+        let buffer_data = [BufferData {
+            buffer_type: BufferType::Mut,
+            start: 0,
+            end: 1,
+            input_constant: 0,
+            output_constant: Cell::new(0),
+        }];
+        let ptrs = [samples.as_mut_ptr()];
+        let buffers = unsafe {
+            Buffers::from_raw_parts(
+                RawBuffers {
+                    buffers: &buffer_data,
+                    ptrs: &ptrs,
+                    offset: 0,
+                },
+                input.len(),
+            )
+        };
+
+        let events: Vec<Event> = bypassed
+            .map(|bypassed| Event {
+                time: 0,
+                data: Data::ParamChange {
+                    id: BYPASS,
+                    value: if bypassed { 1.0 } else { 0.0 },
+                },
+            })
+            .into_iter()
+            .collect();
+
+        let mut queue = EventQueue::with_capacity(0, 0);
+        bypass.process(
+            buffers,
+            Events::new(&events),
+            &mut OutputEvents::new(&mut queue),
+            &Transport::default(),
+        );
+
+        samples
+    }
+
+    #[test]
+    fn dry_signal_delayed_by_latency() {
+        let mut bypass = bypass(4, true);
+
+        let input: Vec<f32> = (1..=16).map(|i| i as f32).collect();
+        let mut output = run(&mut bypass, &input[..8], None);
+        output.extend(run(&mut bypass, &input[8..], None));
+
+        let expected: Vec<f32> = [0.0; 4].into_iter().chain(input[..12].iter().copied()).collect();
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn crossfade_endpoints() {
+        let mut bypass = bypass(0, false);
+
+        let input = [1.0; 16];
+        assert_eq!(run(&mut bypass, &input, None), [2.0; 16]);
+
+        // Fading to the dry signal starts one step away from the processed signal and reaches the
+        // dry signal after the length of the crossfade.
+        let output = run(&mut bypass, &input, Some(true));
+        assert_eq!(output[0], 2.0 - 1.0 / 8.0);
+        assert!(output[..7].windows(2).all(|pair| pair[1] < pair[0]));
+        assert_eq!(output[7..], [1.0; 9]);
+
+        assert_eq!(run(&mut bypass, &input, None), [1.0; 16]);
+
+        let output = run(&mut bypass, &input, Some(false));
+        assert_eq!(output[0], 1.0 + 1.0 / 8.0);
+        assert_eq!(output[7..], [2.0; 9]);
+    }
+
+    #[test]
+    fn reset_after_skipping() {
+        let mut bypass = bypass(4, false);
+
+        let input = [1.0; 16];
+        run(&mut bypass, &input, Some(true));
+        run(&mut bypass, &input, None);
+        assert_eq!(bypass.processor.resets, 0);
+
+        // The processor's delay line is cleared, so it starts over from silence.
+        let output = run(&mut bypass, &input, Some(false));
+        assert_eq!(bypass.processor.resets, 1);
+        assert_eq!(output[8..], [2.0; 8]);
+    }
+}
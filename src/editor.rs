@@ -1,4 +1,5 @@
 use std::ffi::{c_ulong, c_void};
+use std::sync::Arc;
 
 use crate::params::{ParamId, ParamValue};
 
@@ -10,27 +11,248 @@ pub enum RawParent {
 }
 
 pub struct Parent {
-    parent: RawParent,
+    parent: Option<RawParent>,
 }
 
 impl Parent {
     pub unsafe fn from_raw(parent: RawParent) -> Parent {
-        Parent { parent }
+        Parent {
+            parent: Some(parent),
+        }
+    }
+
+    pub(crate) fn floating() -> Parent {
+        Parent { parent: None }
     }
 
-    pub fn as_raw(&self) -> RawParent {
+    // Returns None for floating editors, which must create their own top-level window.
+    pub fn as_raw(&self) -> Option<RawParent> {
         self.parent
     }
 }
 
+pub(crate) trait EditorContextInner {
+    fn begin_gesture(&self, id: ParamId);
+    fn set_value(&self, id: ParamId, value: ParamValue);
+    fn end_gesture(&self, id: ParamId);
+    fn request_resize(&self, size: Size) -> bool;
+}
+
+// Lets an editor change param values. Calls to `set_value` made in response to user input should
+// be bracketed by `begin_gesture` and `end_gesture` so that hosts can record automation properly.
+#[derive(Clone)]
+pub struct EditorContext {
+    inner: Arc<dyn EditorContextInner>,
+}
+
+impl EditorContext {
+    pub(crate) fn from_inner(inner: Arc<dyn EditorContextInner>) -> EditorContext {
+        EditorContext { inner }
+    }
+
+    pub fn begin_gesture(&self, id: ParamId) {
+        self.inner.begin_gesture(id);
+    }
+
+    pub fn set_value(&self, id: ParamId, value: ParamValue) {
+        self.inner.set_value(id, value);
+    }
+
+    pub fn end_gesture(&self, id: ParamId) {
+        self.inner.end_gesture(id);
+    }
+
+    // Asks the host to resize the editor's window. Returns false if the host refused. If the
+    // resize succeeds, the editor is responsible for resizing itself to match.
+    pub fn request_resize(&self, size: Size) -> bool {
+        self.inner.request_resize(size)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Size {
     pub width: f64,
     pub height: f64,
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ResizeHints {
+    pub horizontal: bool,
+    pub vertical: bool,
+    // Width to height ratio to preserve while resizing.
+    pub aspect_ratio: Option<(u32, u32)>,
+    pub min_size: Option<Size>,
+    pub max_size: Option<Size>,
+}
+
+impl Default for ResizeHints {
+    fn default() -> ResizeHints {
+        ResizeHints {
+            horizontal: true,
+            vertical: true,
+            aspect_ratio: None,
+            min_size: None,
+            max_size: None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Key {
+    // A key which produces text, including space.
+    Char(char),
+    Backspace,
+    Tab,
+    Clear,
+    Enter,
+    Pause,
+    Escape,
+    End,
+    Home,
+    Left,
+    Up,
+    Right,
+    Down,
+    PageUp,
+    PageDown,
+    Select,
+    Print,
+    PrintScreen,
+    Insert,
+    Delete,
+    Help,
+    // Numpad digits 0 through 9.
+    Numpad(u8),
+    NumpadMultiply,
+    NumpadAdd,
+    NumpadSeparator,
+    NumpadSubtract,
+    NumpadDecimal,
+    NumpadDivide,
+    NumpadEquals,
+    NumpadEnter,
+    // Function keys F1 through F24.
+    F(u8),
+    NumLock,
+    ScrollLock,
+    Shift,
+    Control,
+    Alt,
+    Super,
+    ContextMenu,
+    MediaPlay,
+    MediaStop,
+    MediaPrevious,
+    MediaNext,
+    VolumeUp,
+    VolumeDown,
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+    // The Command key on macOS, and the Windows or Super key elsewhere.
+    pub meta: bool,
+}
+
 pub trait Editor: Sized + 'static {
     fn size(&self) -> Size;
     fn set_param(&mut self, id: ParamId, value: ParamValue);
+
+    // Resizability is queried without an editor instance, since hosts may ask before creating
+    // the editor.
+    fn can_resize() -> bool {
+        false
+    }
+
+    fn resize_hints() -> ResizeHints {
+        ResizeHints::default()
+    }
+
+    // Returns the closest size to the given one which the editor supports. By default, this
+    // clamps the size to the minimum and maximum sizes given by `resize_hints`.
+    fn constrain_size(size: Size) -> Size {
+        let hints = Self::resize_hints();
+
+        let mut size = size;
+        if let Some(min) = hints.min_size {
+            size.width = size.width.max(min.width);
+            size.height = size.height.max(min.height);
+        }
+        if let Some(max) = hints.max_size {
+            size.width = size.width.min(max.width);
+            size.height = size.height.min(max.height);
+        }
+
+        size
+    }
+
+    // Called when the host resizes the editor's window. Returns false if the size was rejected.
+    #[allow(unused_variables)]
+    fn set_size(&mut self, size: Size) -> bool {
+        false
+    }
+
+    // Whether the editor handles the scale passed to `set_scale` itself. Hosts may set the scale
+    // before the editor is created.
+    fn supports_scaling() -> bool {
+        false
+    }
+
+    // Called with the ratio between physical and logical pixels of the editor's display. Returns
+    // false if the editor doesn't support scaling.
+    #[allow(unused_variables)]
+    fn set_scale(&mut self, scale: f64) -> bool {
+        false
+    }
+
+    // Whether the editor can be created without a parent, in its own top-level window. Hosts
+    // without support for embedding will only open editors which support this.
+    fn supports_floating() -> bool {
+        false
+    }
+
+    // Called for floating editors with the window they should stay on top of.
+    #[allow(unused_variables)]
+    fn set_transient(&mut self, parent: RawParent) -> bool {
+        false
+    }
+
+    // Called for floating editors with a title for their window.
+    #[allow(unused_variables)]
+    fn set_title(&mut self, title: &str) {}
+
+    fn show(&mut self) -> bool {
+        false
+    }
+
+    fn hide(&mut self) -> bool {
+        false
+    }
+
+    #[allow(unused_variables)]
+    fn focus_changed(&mut self, focused: bool) {}
+
+    // Called for keystrokes which the host routes through the plugin's view. Returns true if the
+    // key was handled, otherwise the host may use it for its own shortcuts.
+    #[allow(unused_variables)]
+    fn key_down(&mut self, key: Key, modifiers: Modifiers) -> bool {
+        false
+    }
+
+    #[allow(unused_variables)]
+    fn key_up(&mut self, key: Key, modifiers: Modifiers) -> bool {
+        false
+    }
+
+    // Called for mouse wheel movement which the host routes through the plugin's view. Returns
+    // true if the movement was handled.
+    #[allow(unused_variables)]
+    fn wheel(&mut self, distance: f64) -> bool {
+        false
+    }
 }
 
 pub struct NoEditor;
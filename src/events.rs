@@ -1,28 +1,123 @@
 use std::ops::{Index, RangeBounds};
 use std::slice;
 
+use crate::util::sort_events;
+
 use crate::params::{ParamId, ParamValue};
+use crate::process::Transport;
 
 #[derive(Copy, Clone)]
-pub struct Event {
+pub struct Event<'a> {
     pub time: i64,
-    pub data: Data,
+    pub data: Data<'a>,
+}
+
+pub type NoteId = i32;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Note {
+    pub id: Option<NoteId>,
+    // Ports are identified by their index into `PluginInfo::note_ports`, here and in `Voice`,
+    // `Data::Midi`, and `Data::Sysex`.
+    pub port: usize,
+    pub channel: u8,
+    pub key: u8,
+}
+
+// Identifies the voice(s) targeted by a per-voice event. A field of `None` acts as a wildcard, so
+// the default value targets every voice.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Voice {
+    pub id: Option<NoteId>,
+    pub port: Option<usize>,
+    pub channel: Option<u8>,
+    pub key: Option<u8>,
+}
+
+impl From<Note> for Voice {
+    fn from(note: Note) -> Voice {
+        Voice {
+            id: note.id,
+            port: Some(note.port),
+            channel: Some(note.channel),
+            key: Some(note.key),
+        }
+    }
+}
+
+// Values use the same units as CLAP: volume is a linear gain from 0 to 4, pan goes from 0 (left) to
+// 1 (right), tuning is in semitones from -120 to 120, and the rest range from 0 to 1. VST3 has no
+// pressure expression, so per-note pressure is exchanged as `PolyPressure` events there instead.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum NoteExpression {
+    Volume,
+    Pan,
+    Tuning,
+    Vibrato,
+    Expression,
+    Brightness,
+    Pressure,
 }
 
 #[derive(Copy, Clone)]
 #[non_exhaustive]
-pub enum Data {
-    ParamChange { id: ParamId, value: ParamValue },
+pub enum Data<'a> {
+    ParamChange {
+        id: ParamId,
+        value: ParamValue,
+    },
+    VoiceParamChange {
+        id: ParamId,
+        voice: Voice,
+        value: ParamValue,
+    },
+    // `amount` is an offset in normalized units, like the values of `ParamChange`.
+    ParamMod {
+        id: ParamId,
+        voice: Voice,
+        amount: f64,
+    },
+    NoteOn {
+        note: Note,
+        velocity: f64,
+    },
+    // Note-offs and chokes may use wildcards, e.g. to end every note with a given ID or to choke
+    // every voice.
+    NoteOff {
+        voice: Voice,
+        velocity: f64,
+    },
+    Choke {
+        voice: Voice,
+    },
+    PolyPressure {
+        note: Note,
+        pressure: f64,
+    },
+    NoteExpression {
+        voice: Voice,
+        expression: NoteExpression,
+        value: f64,
+    },
+    Midi {
+        port: usize,
+        bytes: [u8; 3],
+    },
+    Sysex {
+        port: usize,
+        bytes: &'a [u8],
+    },
+    Transport(Transport),
 }
 
 #[derive(Copy, Clone)]
 pub struct Events<'a> {
-    events: &'a [Event],
+    events: &'a [Event<'a>],
 }
 
 impl<'a> Events<'a> {
     #[inline]
-    pub fn new(events: &'a [Event]) -> Events<'a> {
+    pub fn new(events: &'a [Event<'a>]) -> Events<'a> {
         Events { events }
     }
 
@@ -32,7 +127,7 @@ impl<'a> Events<'a> {
     }
 
     #[inline]
-    pub fn get<I>(&self, index: usize) -> Option<&'a Event> {
+    pub fn get<I>(&self, index: usize) -> Option<&'a Event<'a>> {
         self.events.get(index)
     }
 
@@ -49,15 +144,15 @@ impl<'a> Events<'a> {
 }
 
 impl<'a> Index<usize> for Events<'a> {
-    type Output = Event;
+    type Output = Event<'a>;
 
-    fn index(&self, index: usize) -> &Event {
+    fn index(&self, index: usize) -> &Event<'a> {
         &self.events[index]
     }
 }
 
 impl<'a> IntoIterator for Events<'a> {
-    type Item = &'a Event;
+    type Item = &'a Event<'a>;
     type IntoIter = Iter<'a>;
 
     #[inline]
@@ -69,14 +164,105 @@ impl<'a> IntoIterator for Events<'a> {
 }
 
 pub struct Iter<'a> {
-    iter: slice::Iter<'a, Event>,
+    iter: slice::Iter<'a, Event<'a>>,
 }
 
 impl<'a> Iterator for Iter<'a> {
-    type Item = &'a Event;
+    type Item = &'a Event<'a>;
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
         self.iter.next()
     }
 }
+
+#[derive(Copy, Clone, Debug)]
+pub struct PushEventError(pub(crate) ());
+
+pub(crate) struct EventQueue {
+    events: Vec<Event<'static>>,
+    sysex: Vec<u8>,
+}
+
+impl EventQueue {
+    pub fn with_capacity(events: usize, sysex: usize) -> EventQueue {
+        EventQueue {
+            events: Vec::with_capacity(events),
+            sysex: Vec::with_capacity(sysex),
+        }
+    }
+
+    pub fn sorted(&mut self) -> &[Event<'_>] {
+        sort_events(&mut self.events);
+        &self.events
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+        self.sysex.clear();
+    }
+}
+
+pub struct OutputEvents<'a> {
+    queue: &'a mut EventQueue,
+}
+
+impl<'a> OutputEvents<'a> {
+    #[inline]
+    pub(crate) fn new(queue: &'a mut EventQueue) -> OutputEvents<'a> {
+        OutputEvents { queue }
+    }
+
+    pub fn push(&mut self, event: Event) -> Result<(), PushEventError> {
+        // Never grow either buffer, since this is called on the audio thread.
+        if self.queue.events.len() == self.queue.events.capacity() {
+            return Err(PushEventError(()));
+        }
+
+        let data = match event.data {
+            Data::ParamChange { id, value } => Data::ParamChange { id, value },
+            Data::VoiceParamChange { id, voice, value } => {
+                Data::VoiceParamChange { id, voice, value }
+            }
+            Data::ParamMod { id, voice, amount } => Data::ParamMod { id, voice, amount },
+            Data::NoteOn { note, velocity } => Data::NoteOn { note, velocity },
+            Data::NoteOff { voice, velocity } => Data::NoteOff { voice, velocity },
+            Data::Choke { voice } => Data::Choke { voice },
+            Data::PolyPressure { note, pressure } => Data::PolyPressure { note, pressure },
+            Data::NoteExpression {
+                voice,
+                expression,
+                value,
+            } => Data::NoteExpression {
+                voice,
+                expression,
+                value,
+            },
+            Data::Midi { port, bytes } => Data::Midi { port, bytes },
+            Data::Transport(transport) => Data::Transport(transport),
+            Data::Sysex { port, bytes } => {
+                let sysex = &mut self.queue.sysex;
+                if sysex.capacity() - sysex.len() < bytes.len() {
+                    return Err(PushEventError(()));
+                }
+
+                let start = sysex.len();
+                sysex.extend_from_slice(bytes);
+
+                // The sysex buffer is never reallocated, so the copied payload stays in place
+                // until the queue is cleared by the format wrapper.
+                let bytes =
+                    unsafe { slice::from_raw_parts(sysex.as_ptr().add(start), bytes.len()) };
+
+                Data::Sysex { port, bytes }
+            }
+        };
+
+        self.queue.events.push(Event {
+            time: event.time,
+            data,
+        });
+
+        Ok(())
+    }
+}
@@ -4,7 +4,7 @@ use clap_sys::ext::gui::*;
 use clap_sys::plugin::*;
 
 use super::instance::Instance;
-use crate::editor::{Editor, Parent, RawParent};
+use crate::editor::{Editor, EditorContext, Parent, RawParent, Size};
 use crate::plugin::Plugin;
 
 impl<P: Plugin> Instance<P> {
@@ -35,12 +35,48 @@ impl<P: Plugin> Instance<P> {
     #[cfg(target_os = "linux")]
     const API: &'static CStr = CLAP_WINDOW_API_X11;
 
+    unsafe fn raw_parent(window: &clap_window) -> Option<RawParent> {
+        if CStr::from_ptr(window.api) != Self::API {
+            return None;
+        }
+
+        #[cfg(target_os = "windows")]
+        let raw_parent = { RawParent::Win32(window.specific.win32) };
+
+        #[cfg(target_os = "macos")]
+        let raw_parent = { RawParent::Cocoa(window.specific.cocoa) };
+
+        #[cfg(target_os = "linux")]
+        let raw_parent = { RawParent::X11(window.specific.x11) };
+
+        Some(raw_parent)
+    }
+
+    unsafe fn create_editor(&self, parent: Parent) {
+        let main_thread_state = &mut *self.main_thread_state.get();
+
+        self.sync_plugin(&mut main_thread_state.plugin);
+
+        let context = EditorContext::from_inner(self.host.clone());
+        let editor = main_thread_state.plugin.editor(context, parent);
+        main_thread_state.editor = Some(editor);
+
+        if let (Some(editor), Some(scale)) = (
+            &mut main_thread_state.editor,
+            main_thread_state.editor_scale,
+        ) {
+            editor.set_scale(scale);
+        }
+
+        self.sync_editor(main_thread_state.editor.as_mut());
+    }
+
     unsafe extern "C" fn gui_is_api_supported(
         _plugin: *const clap_plugin,
         api: *const c_char,
         is_floating: bool,
     ) -> bool {
-        if is_floating {
+        if is_floating && !P::Editor::supports_floating() {
             return false;
         }
 
@@ -68,6 +104,12 @@ impl<P: Plugin> Instance<P> {
             return false;
         }
 
+        // Floating editors never receive a parent, so they are created right away.
+        if is_floating {
+            let instance = &*(plugin as *const Self);
+            instance.create_editor(Parent::floating());
+        }
+
         true
     }
 
@@ -76,10 +118,20 @@ impl<P: Plugin> Instance<P> {
         let main_thread_state = &mut *instance.main_thread_state.get();
 
         main_thread_state.editor = None;
+        main_thread_state.editor_scale = None;
     }
 
-    unsafe extern "C" fn gui_set_scale(_plugin: *const clap_plugin, _scale: f64) -> bool {
-        false
+    unsafe extern "C" fn gui_set_scale(plugin: *const clap_plugin, scale: f64) -> bool {
+        let instance = &*(plugin as *const Self);
+        let main_thread_state = &mut *instance.main_thread_state.get();
+
+        main_thread_state.editor_scale = Some(scale);
+
+        if let Some(editor) = &mut main_thread_state.editor {
+            return editor.set_scale(scale);
+        }
+
+        P::Editor::supports_scaling()
     }
 
     unsafe extern "C" fn gui_get_size(
@@ -103,29 +155,66 @@ impl<P: Plugin> Instance<P> {
     }
 
     unsafe extern "C" fn gui_can_resize(_plugin: *const clap_plugin) -> bool {
-        false
+        P::Editor::can_resize()
     }
 
     unsafe extern "C" fn gui_get_resize_hints(
         _plugin: *const clap_plugin,
-        _hints: *mut clap_gui_resize_hints,
+        hints: *mut clap_gui_resize_hints,
     ) -> bool {
-        false
+        if !P::Editor::can_resize() {
+            return false;
+        }
+
+        let editor_hints = P::Editor::resize_hints();
+
+        let hints = &mut *hints;
+        hints.can_resize_horizontally = editor_hints.horizontal;
+        hints.can_resize_vertically = editor_hints.vertical;
+        if let Some((width, height)) = editor_hints.aspect_ratio {
+            hints.preserve_aspect_ratio = true;
+            hints.aspect_ratio_width = width;
+            hints.aspect_ratio_height = height;
+        } else {
+            hints.preserve_aspect_ratio = false;
+            hints.aspect_ratio_width = 0;
+            hints.aspect_ratio_height = 0;
+        }
+
+        true
     }
 
     unsafe extern "C" fn gui_adjust_size(
         _plugin: *const clap_plugin,
-        _width: *mut u32,
-        _height: *mut u32,
+        width: *mut u32,
+        height: *mut u32,
     ) -> bool {
-        false
+        if !P::Editor::can_resize() {
+            return false;
+        }
+
+        let size = P::Editor::constrain_size(Size {
+            width: *width as f64,
+            height: *height as f64,
+        });
+
+        *width = size.width.round() as u32;
+        *height = size.height.round() as u32;
+
+        true
     }
 
-    unsafe extern "C" fn gui_set_size(
-        _plugin: *const clap_plugin,
-        _width: u32,
-        _height: u32,
-    ) -> bool {
+    unsafe extern "C" fn gui_set_size(plugin: *const clap_plugin, width: u32, height: u32) -> bool {
+        let instance = &*(plugin as *const Self);
+        let main_thread_state = &mut *instance.main_thread_state.get();
+
+        if let Some(editor) = &mut main_thread_state.editor {
+            return editor.set_size(Size {
+                width: width as f64,
+                height: height as f64,
+            });
+        }
+
         false
     }
 
@@ -133,44 +222,62 @@ impl<P: Plugin> Instance<P> {
         plugin: *const clap_plugin,
         window: *const clap_window,
     ) -> bool {
-        let window = &*window;
-
-        if CStr::from_ptr(window.api) != Self::API {
+        let Some(raw_parent) = Self::raw_parent(&*window) else {
             return false;
-        }
+        };
 
-        #[cfg(target_os = "windows")]
-        let raw_parent = { RawParent::Win32(window.specific.win32) };
+        let instance = &*(plugin as *const Self);
+        instance.create_editor(Parent::from_raw(raw_parent));
 
-        #[cfg(target_os = "macos")]
-        let raw_parent = { RawParent::Cocoa(window.specific.cocoa) };
+        true
+    }
 
-        #[cfg(target_os = "linux")]
-        let raw_parent = { RawParent::X11(window.specific.x11) };
+    unsafe extern "C" fn gui_set_transient(
+        plugin: *const clap_plugin,
+        window: *const clap_window,
+    ) -> bool {
+        let Some(raw_parent) = Self::raw_parent(&*window) else {
+            return false;
+        };
 
         let instance = &*(plugin as *const Self);
         let main_thread_state = &mut *instance.main_thread_state.get();
 
-        let editor = main_thread_state.plugin.editor(Parent::from_raw(raw_parent));
-        main_thread_state.editor = Some(editor);
+        if let Some(editor) = &mut main_thread_state.editor {
+            return editor.set_transient(raw_parent);
+        }
 
-        true
+        false
     }
 
-    unsafe extern "C" fn gui_set_transient(
-        _plugin: *const clap_plugin,
-        _window: *const clap_window,
-    ) -> bool {
-        false
+    unsafe extern "C" fn gui_suggest_title(plugin: *const clap_plugin, title: *const c_char) {
+        let instance = &*(plugin as *const Self);
+        let main_thread_state = &mut *instance.main_thread_state.get();
+
+        if let Some(editor) = &mut main_thread_state.editor {
+            editor.set_title(&CStr::from_ptr(title).to_string_lossy());
+        }
     }
 
-    unsafe extern "C" fn gui_suggest_title(_plugin: *const clap_plugin, _title: *const c_char) {}
+    unsafe extern "C" fn gui_show(plugin: *const clap_plugin) -> bool {
+        let instance = &*(plugin as *const Self);
+        let main_thread_state = &mut *instance.main_thread_state.get();
+
+        if let Some(editor) = &mut main_thread_state.editor {
+            return editor.show();
+        }
 
-    unsafe extern "C" fn gui_show(_plugin: *const clap_plugin) -> bool {
         false
     }
 
-    unsafe extern "C" fn gui_hide(_plugin: *const clap_plugin) -> bool {
+    unsafe extern "C" fn gui_hide(plugin: *const clap_plugin) -> bool {
+        let instance = &*(plugin as *const Self);
+        let main_thread_state = &mut *instance.main_thread_state.get();
+
+        if let Some(editor) = &mut main_thread_state.editor {
+            return editor.hide();
+        }
+
         false
     }
 }
@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::ffi::CStr;
+#[cfg(unix)]
+use std::os::fd::RawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use clap_sys::ext::gui::*;
+use clap_sys::ext::params::*;
+#[cfg(unix)]
+use clap_sys::ext::posix_fd_support::*;
+use clap_sys::ext::timer_support::*;
+use clap_sys::host::*;
+use clap_sys::id::{clap_id, CLAP_INVALID_ID};
+
+use crate::editor::{EditorContextInner, Size};
+use crate::params::{ParamId, ParamInfo, ParamValue};
+#[cfg(unix)]
+use crate::plugin::{FdFlags, FdFn};
+use crate::plugin::{HostInner, TimerFn, TimerId};
+
+pub enum ParamEdit {
+    Begin(ParamId),
+    Set(ParamId, ParamValue),
+    End(ParamId),
+}
+
+pub struct ClapHost {
+    pub host: *const clap_host,
+    // Host callbacks may not be used until `clap_plugin::init` has been called, which happens after
+    // `Plugin::new` has returned.
+    pub initialized: AtomicBool,
+    pub latency_changed: AtomicBool,
+    pub tail_changed: AtomicBool,
+    pub params: Mutex<Option<Vec<ParamInfo>>>,
+    pub param_values_changed: AtomicBool,
+    // Edits made through `EditorContext`, which are sent to the host from `process` or
+    // `clap_plugin_params::flush`.
+    pub edits: Mutex<Vec<ParamEdit>>,
+    // Callbacks are reference-counted so that they can be called without holding the lock, which
+    // lets them register or unregister callbacks themselves.
+    pub timers: Mutex<HashMap<clap_id, Arc<Mutex<Box<TimerFn>>>>>,
+    #[cfg(unix)]
+    pub fds: Mutex<HashMap<RawFd, Arc<Mutex<Box<FdFn>>>>>,
+}
+
+unsafe impl Send for ClapHost {}
+unsafe impl Sync for ClapHost {}
+
+impl ClapHost {
+    pub fn new(host: *const clap_host) -> ClapHost {
+        ClapHost {
+            host,
+            initialized: AtomicBool::new(false),
+            latency_changed: AtomicBool::new(false),
+            tail_changed: AtomicBool::new(false),
+            params: Mutex::new(None),
+            param_values_changed: AtomicBool::new(false),
+            edits: Mutex::new(Vec::new()),
+            timers: Mutex::new(HashMap::new()),
+            #[cfg(unix)]
+            fds: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub unsafe fn get_extension<T>(&self, id: &CStr) -> Option<&T> {
+        let extension = (*self.host).get_extension.unwrap()(self.host, id.as_ptr());
+        (extension as *const T).as_ref()
+    }
+
+    // Before init, this is skipped, and init requests a callback instead.
+    pub fn request_callback(&self) {
+        if !self.initialized.load(Ordering::Relaxed) {
+            return;
+        }
+
+        unsafe { (*self.host).request_callback.unwrap()(self.host) };
+    }
+
+    // Unregisters any timers and fds which the plugin left registered. Must be called on the main
+    // thread before the plugin is destroyed.
+    pub fn unregister_all(&self) {
+        let timers: Vec<clap_id> = self.timers.lock().unwrap().keys().copied().collect();
+        for id in timers {
+            self.unregister_timer(TimerId(id));
+        }
+
+        #[cfg(unix)]
+        {
+            let fds: Vec<RawFd> = self.fds.lock().unwrap().keys().copied().collect();
+            for fd in fds {
+                self.unregister_fd(fd);
+            }
+        }
+    }
+
+    fn push_edit(&self, edit: ParamEdit) {
+        self.edits.lock().unwrap().push(edit);
+
+        unsafe {
+            if let Some(params) = self.get_extension::<clap_host_params>(CLAP_EXT_PARAMS) {
+                params.request_flush.unwrap()(self.host);
+            }
+        }
+    }
+}
+
+// All of these are called on the main thread, as documented on `Host`. Notifications are still
+// deferred to `clap_plugin::on_main_thread`, since the plugin may be in the middle of a call from the
+// host (e.g. `activate`) at the time.
+impl HostInner for ClapHost {
+    fn latency_changed(&self) {
+        self.latency_changed.store(true, Ordering::Relaxed);
+        self.request_callback();
+    }
+
+    fn tail_changed(&self) {
+        self.tail_changed.store(true, Ordering::Relaxed);
+        self.request_callback();
+    }
+
+    fn set_params(&self, params: Vec<ParamInfo>) {
+        *self.params.lock().unwrap() = Some(params);
+        self.request_callback();
+    }
+
+    fn param_values_changed(&self) {
+        self.param_values_changed.store(true, Ordering::Relaxed);
+        self.request_callback();
+    }
+
+    fn register_timer(&self, period_ms: u32, callback: Box<TimerFn>) -> Option<TimerId> {
+        if !self.initialized.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        unsafe {
+            let timer_support =
+                self.get_extension::<clap_host_timer_support>(CLAP_EXT_TIMER_SUPPORT)?;
+
+            let mut id = CLAP_INVALID_ID;
+            if !timer_support.register_timer.unwrap()(self.host, period_ms, &mut id) {
+                return None;
+            }
+
+            self.timers.lock().unwrap().insert(id, Arc::new(Mutex::new(callback)));
+
+            Some(TimerId(id))
+        }
+    }
+
+    fn unregister_timer(&self, id: TimerId) {
+        if self.timers.lock().unwrap().remove(&id.0).is_none() {
+            return;
+        }
+
+        unsafe {
+            if let Some(timer_support) =
+                self.get_extension::<clap_host_timer_support>(CLAP_EXT_TIMER_SUPPORT)
+            {
+                timer_support.unregister_timer.unwrap()(self.host, id.0);
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    fn register_fd(&self, fd: RawFd, flags: FdFlags, callback: Box<FdFn>) -> bool {
+        if !self.initialized.load(Ordering::Relaxed) || self.fds.lock().unwrap().contains_key(&fd) {
+            return false;
+        }
+
+        let mut clap_flags = 0;
+        if flags.read {
+            clap_flags |= CLAP_POSIX_FD_READ;
+        }
+        if flags.write {
+            clap_flags |= CLAP_POSIX_FD_WRITE;
+        }
+        if flags.error {
+            clap_flags |= CLAP_POSIX_FD_ERROR;
+        }
+
+        unsafe {
+            let Some(fd_support) =
+                self.get_extension::<clap_host_posix_fd_support>(CLAP_EXT_POSIX_FD_SUPPORT)
+            else {
+                return false;
+            };
+
+            if !fd_support.register_fd.unwrap()(self.host, fd, clap_flags) {
+                return false;
+            }
+        }
+
+        self.fds.lock().unwrap().insert(fd, Arc::new(Mutex::new(callback)));
+
+        true
+    }
+
+    #[cfg(unix)]
+    fn unregister_fd(&self, fd: RawFd) {
+        if self.fds.lock().unwrap().remove(&fd).is_none() {
+            return;
+        }
+
+        unsafe {
+            if let Some(fd_support) =
+                self.get_extension::<clap_host_posix_fd_support>(CLAP_EXT_POSIX_FD_SUPPORT)
+            {
+                fd_support.unregister_fd.unwrap()(self.host, fd);
+            }
+        }
+    }
+}
+
+impl EditorContextInner for ClapHost {
+    fn begin_gesture(&self, id: ParamId) {
+        self.push_edit(ParamEdit::Begin(id));
+    }
+
+    fn set_value(&self, id: ParamId, value: ParamValue) {
+        self.push_edit(ParamEdit::Set(id, value));
+    }
+
+    fn end_gesture(&self, id: ParamId) {
+        self.push_edit(ParamEdit::End(id));
+    }
+
+    fn request_resize(&self, size: Size) -> bool {
+        unsafe {
+            if let Some(gui) = self.get_extension::<clap_host_gui>(CLAP_EXT_GUI) {
+                let width = size.width.round() as u32;
+                let height = size.height.round() as u32;
+                return gui.request_resize.unwrap()(self.host, width, height);
+            }
+        }
+
+        false
+    }
+}
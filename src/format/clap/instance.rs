@@ -1,23 +1,35 @@
-use std::cell::UnsafeCell;
-use std::collections::HashMap;
+use std::cell::{Cell, UnsafeCell};
 use std::ffi::{c_char, c_void, CStr};
 use std::iter::zip;
 use std::ptr::NonNull;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
-use std::{io, ptr, slice};
+use std::{io, mem, ptr, slice};
 
-use clap_sys::ext::{audio_ports::*, audio_ports_config::*, gui::*, params::*, state::*};
-use clap_sys::{events::*, id::*, plugin::*, process::*, stream::*};
+#[cfg(unix)]
+use clap_sys::ext::posix_fd_support::*;
+use clap_sys::ext::{
+    audio_ports::*, audio_ports_config::*, gui::*, latency::*, note_ports::*, params::*, state::*,
+    tail::*, timer_support::*,
+};
+use clap_sys::{events::*, fixedpoint::*, host::*, id::*, plugin::*, process::*, stream::*};
+
+use super::host::{ClapHost, ParamEdit};
 
 use crate::buffers::{BufferData, BufferType, BufferView, Buffers, RawBuffers};
-use crate::bus::{BusDir, Format};
-use crate::events::{Data, Event, Events};
-use crate::params::{ParamId, ParamInfo, ParamValue};
-use crate::plugin::{Host, Plugin, PluginInfo};
-use crate::process::{Config, Processor};
-use crate::sync::params::ParamValues;
+use crate::bus::{BusDir, Format, NoteDialect};
+use crate::bypass::Bypass;
+use crate::editor::Editor;
+use crate::events::{Data, Event, EventQueue, Events, Note, NoteExpression, OutputEvents, Voice};
+use crate::format::params::ParamState;
+use crate::params::{ParamInfo, ParamValue};
+use crate::plugin::{Host, Plugin, PluginInfo, Tail};
+use crate::process::{Config, Processor, Status, Transport};
 use crate::util::{copy_cstring, slice_from_raw_parts_checked, DisplayParam};
 
+// Not yet provided by clap-sys.
+const CLAP_PARAM_IS_ENUM: clap_param_info_flags = 1 << 16;
+
 fn port_type_from_format(format: &Format) -> &'static CStr {
     match format {
         Format::Mono => CLAP_PORT_MONO,
@@ -26,44 +38,178 @@ fn port_type_from_format(format: &Format) -> &'static CStr {
 }
 
 fn map_param_in(param: &ParamInfo, value: f64) -> ParamValue {
-    if let Some(steps) = param.steps {
-        (value + 0.5) / steps as f64
-    } else {
-        value
-    }
+    (param.from_plain)(value).clamp(0.0, 1.0)
 }
 
 fn map_param_out(param: &ParamInfo, value: ParamValue) -> f64 {
+    (param.to_plain)(value)
+}
+
+fn event_header<T>(time: i64, type_: u16) -> clap_event_header {
+    clap_event_header {
+        size: mem::size_of::<T>() as u32,
+        time: time.max(0) as u32,
+        space_id: CLAP_CORE_EVENT_SPACE_ID,
+        type_,
+        flags: 0,
+    }
+}
+
+fn transport_from_clap(transport: &clap_event_transport) -> Transport {
+    let beats = |time: clap_beattime| time as f64 / CLAP_BEATTIME_FACTOR as f64;
+    let has = |flag: clap_transport_flags| transport.flags & flag != 0;
+
+    Transport {
+        playing: has(CLAP_TRANSPORT_IS_PLAYING),
+        recording: has(CLAP_TRANSPORT_IS_RECORDING),
+        looping: has(CLAP_TRANSPORT_IS_LOOP_ACTIVE),
+        tempo: has(CLAP_TRANSPORT_HAS_TEMPO).then_some(transport.tempo),
+        time_signature: has(CLAP_TRANSPORT_HAS_TIME_SIGNATURE)
+            .then_some((transport.tsig_num, transport.tsig_denom)),
+        pos_beats: has(CLAP_TRANSPORT_HAS_BEATS_TIMELINE).then(|| beats(transport.song_pos_beats)),
+        pos_seconds: has(CLAP_TRANSPORT_HAS_SECONDS_TIMELINE)
+            .then(|| transport.song_pos_seconds as f64 / CLAP_SECTIME_FACTOR as f64),
+        bar_start_beats: has(CLAP_TRANSPORT_HAS_BEATS_TIMELINE).then(|| beats(transport.bar_start)),
+        loop_range_beats: has(CLAP_TRANSPORT_HAS_BEATS_TIMELINE).then(|| {
+            (
+                beats(transport.loop_start_beats),
+                beats(transport.loop_end_beats),
+            )
+        }),
+    }
+}
+
+// CLAP mod amounts are offsets in plain units, so convert them to offsets in normalized units. This
+// is exact for stepped params and linear ranges, and a linear approximation for other ranges.
+fn map_param_mod(param: &ParamInfo, amount: f64) -> f64 {
     if let Some(steps) = param.steps {
-        (value * steps as f64).floor()
+        amount / steps as f64
+    } else if param.max != param.min {
+        amount / (param.max - param.min)
     } else {
-        value
+        0.0
+    }
+}
+
+// `port_map` maps the host's port indices for one direction to indices into
+// `PluginInfo::note_ports`. Returns None if the event names a port which doesn't exist, since
+// treating it as a wildcard would target every port.
+fn voice_from_clap(
+    port_map: &[usize],
+    note_id: i32,
+    port_index: i16,
+    channel: i16,
+    key: i16,
+) -> Option<Voice> {
+    let port = match usize::try_from(port_index) {
+        Ok(port) => Some(*port_map.get(port)?),
+        Err(_) => None,
+    };
+
+    Some(Voice {
+        id: if note_id >= 0 { Some(note_id) } else { None },
+        port,
+        channel: u8::try_from(channel).ok(),
+        key: u8::try_from(key).ok(),
+    })
+}
+
+fn note_expression_from_clap(expression_id: clap_note_expression) -> Option<NoteExpression> {
+    match expression_id {
+        CLAP_NOTE_EXPRESSION_VOLUME => Some(NoteExpression::Volume),
+        CLAP_NOTE_EXPRESSION_PAN => Some(NoteExpression::Pan),
+        CLAP_NOTE_EXPRESSION_TUNING => Some(NoteExpression::Tuning),
+        CLAP_NOTE_EXPRESSION_VIBRATO => Some(NoteExpression::Vibrato),
+        CLAP_NOTE_EXPRESSION_EXPRESSION => Some(NoteExpression::Expression),
+        CLAP_NOTE_EXPRESSION_BRIGHTNESS => Some(NoteExpression::Brightness),
+        CLAP_NOTE_EXPRESSION_PRESSURE => Some(NoteExpression::Pressure),
+        _ => None,
+    }
+}
+
+fn note_expression_to_clap(expression: NoteExpression) -> clap_note_expression {
+    match expression {
+        NoteExpression::Volume => CLAP_NOTE_EXPRESSION_VOLUME,
+        NoteExpression::Pan => CLAP_NOTE_EXPRESSION_PAN,
+        NoteExpression::Tuning => CLAP_NOTE_EXPRESSION_TUNING,
+        NoteExpression::Vibrato => CLAP_NOTE_EXPRESSION_VIBRATO,
+        NoteExpression::Expression => CLAP_NOTE_EXPRESSION_EXPRESSION,
+        NoteExpression::Brightness => CLAP_NOTE_EXPRESSION_BRIGHTNESS,
+        NoteExpression::Pressure => CLAP_NOTE_EXPRESSION_PRESSURE,
+    }
+}
+
+fn note_dialect_to_clap(dialect: NoteDialect) -> clap_note_dialect {
+    match dialect {
+        NoteDialect::Clap => CLAP_NOTE_DIALECT_CLAP,
+        NoteDialect::Midi => CLAP_NOTE_DIALECT_MIDI,
+        NoteDialect::Midi2 => CLAP_NOTE_DIALECT_MIDI2,
+    }
+}
+
+fn tail_to_clap(tail: Tail) -> u32 {
+    // Any value greater than or equal to i32::MAX means an infinite tail.
+    match tail {
+        Tail::None => 0,
+        Tail::Samples(samples) => samples.min(i32::MAX as u64 - 1) as u32,
+        Tail::Infinite => u32::MAX,
     }
 }
 
+fn note_from_clap(
+    port_map: &[usize],
+    note_id: i32,
+    port_index: i16,
+    channel: i16,
+    key: i16,
+) -> Option<Note> {
+    Some(Note {
+        id: if note_id >= 0 { Some(note_id) } else { None },
+        port: *port_map.get(usize::try_from(port_index).ok()?)?,
+        channel: u8::try_from(channel).ok()?,
+        key: u8::try_from(key).ok()?,
+    })
+}
+
 pub struct MainThreadState<P: Plugin> {
     pub layout_index: usize,
+    // Only set while the plugin is activated.
+    pub config: Option<Config>,
     pub plugin: P,
     pub editor: Option<P::Editor>,
+    // The host may set the scale before the editor is created.
+    pub editor_scale: Option<f64>,
+    // Set once a restart has been requested in order to apply a new param list, so that it is
+    // only requested once.
+    pub params_restart_requested: bool,
+    // Set when a new param list has been applied but the host hasn't been told to rescan it yet.
+    pub params_rescan_pending: bool,
 }
 
 pub struct ProcessState<P: Plugin> {
     buffer_data: Vec<BufferData>,
     buffer_ptrs: Vec<*mut f32>,
-    events: Vec<Event>,
-    processor: Option<P::Processor>,
+    events: Vec<Event<'static>>,
+    output_events: EventQueue,
+    processor: Option<Bypass<P::Processor>>,
 }
 
 #[repr(C)]
 pub struct Instance<P: Plugin> {
     #[allow(unused)]
     pub clap_plugin: clap_plugin,
+    pub host: Arc<ClapHost>,
     pub info: Arc<PluginInfo>,
     pub input_bus_map: Vec<usize>,
     pub output_bus_map: Vec<usize>,
-    pub param_map: HashMap<ParamId, usize>,
-    pub plugin_params: ParamValues,
-    pub processor_params: ParamValues,
+    pub note_input_map: Vec<usize>,
+    pub note_output_map: Vec<usize>,
+    // Only replaced on the main thread while the plugin is deactivated.
+    pub param_state: UnsafeCell<ParamState>,
+    // The tail extension may be queried from the audio thread, so the tail length is cached here
+    // whenever it is computed on the main thread.
+    pub tail: AtomicU32,
+    pub tail_changed: AtomicBool,
     pub main_thread_state: UnsafeCell<MainThreadState<P>>,
     pub process_state: UnsafeCell<ProcessState<P>>,
 }
@@ -71,7 +217,13 @@ pub struct Instance<P: Plugin> {
 unsafe impl<P: Plugin> Sync for Instance<P> {}
 
 impl<P: Plugin> Instance<P> {
-    pub fn new(desc: *const clap_plugin_descriptor, info: &Arc<PluginInfo>) -> Self {
+    pub fn new(
+        host: *const clap_host,
+        desc: *const clap_plugin_descriptor,
+        info: &Arc<PluginInfo>,
+    ) -> Self {
+        let host = Arc::new(ClapHost::new(host));
+
         let mut input_bus_map = Vec::new();
         let mut output_bus_map = Vec::new();
         for (index, bus) in info.buses.iter().enumerate() {
@@ -85,9 +237,17 @@ impl<P: Plugin> Instance<P> {
             }
         }
 
-        let mut param_map = HashMap::new();
-        for (index, param) in info.params.iter().enumerate() {
-            param_map.insert(param.id, index);
+        let mut note_input_map = Vec::new();
+        let mut note_output_map = Vec::new();
+        for (index, port) in info.note_ports.iter().enumerate() {
+            match port.dir {
+                BusDir::In => note_input_map.push(index),
+                BusDir::Out => note_output_map.push(index),
+                BusDir::InOut => {
+                    note_input_map.push(index);
+                    note_output_map.push(index);
+                }
+            }
         }
 
         Instance {
@@ -105,47 +265,108 @@ impl<P: Plugin> Instance<P> {
                 get_extension: Some(Self::get_extension),
                 on_main_thread: Some(Self::on_main_thread),
             },
+            host: host.clone(),
             info: info.clone(),
             input_bus_map,
             output_bus_map,
-            param_map,
-            plugin_params: ParamValues::new(&info.params),
-            processor_params: ParamValues::new(&info.params),
+            note_input_map,
+            note_output_map,
+            param_state: UnsafeCell::new(ParamState::new(info, None)),
+            tail: AtomicU32::new(0),
+            tail_changed: AtomicBool::new(false),
             main_thread_state: UnsafeCell::new(MainThreadState {
                 layout_index: 0,
-                plugin: P::new(Host {}),
+                config: None,
+                plugin: P::new(Host::from_inner(host)),
                 editor: None,
+                editor_scale: None,
+                params_restart_requested: false,
+                params_rescan_pending: false,
             }),
             process_state: UnsafeCell::new(ProcessState {
                 buffer_data: Vec::new(),
                 buffer_ptrs: Vec::new(),
                 events: Vec::with_capacity(4096),
+                output_events: EventQueue::with_capacity(4096, 65536),
                 processor: None,
             }),
         }
     }
 
-    fn sync_plugin(&self, plugin: &mut P) {
-        for (index, value) in self.plugin_params.poll() {
-            let id = self.info.params[index].id;
+    fn param_state(&self) -> &ParamState {
+        unsafe { &*self.param_state.get() }
+    }
+
+    // Applies a param list passed to `Host::set_params`. Must only be called on the main thread
+    // while the plugin is deactivated. The host is told to rescan the list from `on_main_thread`.
+    unsafe fn update_params(&self, main_thread_state: &mut MainThreadState<P>) {
+        let Some(params) = self.host.params.lock().unwrap().take() else {
+            return;
+        };
+
+        // Deliver any pending changes before the old param indices become meaningless.
+        self.sync_plugin(&mut main_thread_state.plugin);
+        *self.param_state.get() = ParamState::new(&self.info, Some(params));
+
+        main_thread_state.params_rescan_pending = true;
+    }
+
+    // Passes param values changed by the plugin itself on to the processor and the host. Must only
+    // be called on the main thread.
+    unsafe fn push_param_values(&self, plugin: &P) {
+        let param_state = self.param_state();
+        for (index, param) in param_state.params().iter().enumerate() {
+            let value = plugin.get_param(param.id);
+            param_state.processor_params.set(index, value);
+            param_state.editor_params.set(index, value);
+        }
+
+        if let Some(host_params) = self.host.get_extension::<clap_host_params>(CLAP_EXT_PARAMS) {
+            host_params.rescan.unwrap()(self.host.host, CLAP_PARAM_RESCAN_VALUES);
+            host_params.request_flush.unwrap()(self.host.host);
+        }
+    }
+
+    pub(super) fn sync_plugin(&self, plugin: &mut P) {
+        for (index, value) in self.param_state().plugin_params.poll() {
+            let id = self.param_state().params()[index].id;
             plugin.set_param(id, value);
         }
     }
 
-    fn sync_processor(&self, processor: &mut P::Processor) {
-        for (index, value) in self.processor_params.poll() {
-            let id = self.info.params[index].id;
+    pub(super) fn sync_editor(&self, editor: Option<&mut P::Editor>) {
+        let param_state = self.param_state();
+        let changes = param_state.editor_params.poll();
+        if let Some(editor) = editor {
+            for (index, value) in changes {
+                editor.set_param(param_state.params()[index].id, value);
+            }
+        }
+    }
+
+    fn sync_processor(&self, processor: &mut Bypass<P::Processor>) {
+        for (index, value) in self.param_state().processor_params.poll() {
+            let id = self.param_state().params()[index].id;
             processor.set_param(id, value);
         }
     }
 }
 
 impl<P: Plugin> Instance<P> {
-    unsafe extern "C" fn init(_plugin: *const clap_plugin) -> bool {
+    unsafe extern "C" fn init(plugin: *const clap_plugin) -> bool {
+        let instance = &*(plugin as *const Self);
+
+        // Deliver any notifications sent from `Plugin::new`.
+        instance.host.initialized.store(true, Ordering::Relaxed);
+        instance.host.request_callback();
+
         true
     }
 
     unsafe extern "C" fn destroy(plugin: *const clap_plugin) {
+        let instance = &*(plugin as *const Self);
+        instance.host.unregister_all();
+
         drop(Box::from_raw(plugin as *mut Self));
     }
 
@@ -174,6 +395,8 @@ impl<P: Plugin> Instance<P> {
                 buffer_type,
                 start: total_channels,
                 end: total_channels + channel_count,
+                input_constant: 0,
+                output_constant: Cell::new(0),
             });
 
             total_channels += channel_count;
@@ -188,16 +411,32 @@ impl<P: Plugin> Instance<P> {
         };
 
         instance.sync_plugin(&mut main_thread_state.plugin);
-        process_state.processor = Some(main_thread_state.plugin.processor(config));
+        let tail = main_thread_state.plugin.tail(&config);
+        instance.tail.store(tail_to_clap(tail), Ordering::Relaxed);
+        process_state.processor = Some(Bypass::new(
+            &main_thread_state.plugin,
+            &instance.info,
+            config.clone(),
+        ));
+        main_thread_state.config = Some(config);
 
         true
     }
 
     unsafe extern "C" fn deactivate(plugin: *const clap_plugin) {
         let instance = &*(plugin as *const Self);
+        let main_thread_state = &mut *instance.main_thread_state.get();
         let process_state = &mut *instance.process_state.get();
 
         process_state.processor = None;
+        main_thread_state.config = None;
+
+        // Rescanning the full param list is only allowed once deactivate has returned, so that is
+        // left to on_main_thread.
+        instance.update_params(main_thread_state);
+        if main_thread_state.params_rescan_pending {
+            instance.host.request_callback();
+        }
     }
 
     unsafe extern "C" fn start_processing(_plugin: *const clap_plugin) -> bool {
@@ -242,6 +481,11 @@ impl<P: Plugin> Instance<P> {
         let inputs = slice_from_raw_parts_checked(process.audio_inputs, input_count);
         let outputs = slice_from_raw_parts_checked(process.audio_outputs, output_count);
 
+        for data in &mut process_state.buffer_data {
+            data.input_constant = 0;
+            data.output_constant.set(0);
+        }
+
         for (&bus_index, output) in zip(&instance.output_bus_map, outputs) {
             let data = &process_state.buffer_data[bus_index];
 
@@ -267,6 +511,7 @@ impl<P: Plugin> Instance<P> {
             let channels =
                 slice_from_raw_parts_checked(input.data32 as *const *mut f32, channel_count);
             let ptrs = &mut process_state.buffer_ptrs[data.start..data.end];
+            process_state.buffer_data[bus_index].input_constant = input.constant_mask;
 
             match bus_info.dir {
                 BusDir::In => {
@@ -285,36 +530,222 @@ impl<P: Plugin> Instance<P> {
             }
         }
 
+        if instance.tail_changed.swap(false, Ordering::Relaxed) {
+            if let Some(tail) = instance.host.get_extension::<clap_host_tail>(CLAP_EXT_TAIL) {
+                tail.changed.unwrap()(instance.host.host);
+            }
+        }
+
         process_state.events.clear();
 
+        // Set whenever the editor needs to be notified of new param values.
+        let mut editor_changed = false;
+
         let in_events = process.in_events;
         let size = (*in_events).size.unwrap()(in_events);
         for i in 0..size {
             let event = (*in_events).get.unwrap()(in_events, i);
 
-            if (*event).space_id == CLAP_CORE_EVENT_SPACE_ID
-                && (*event).type_ == CLAP_EVENT_PARAM_VALUE
-            {
-                let event = &*(event as *const clap_event_param_value);
+            if (*event).space_id != CLAP_CORE_EVENT_SPACE_ID {
+                continue;
+            }
 
-                if let Some(&index) = instance.param_map.get(&event.param_id) {
-                    let value = map_param_in(&instance.info.params[index], event.value);
+            match (*event).type_ {
+                CLAP_EVENT_PARAM_VALUE => {
+                    let event = &*(event as *const clap_event_param_value);
+
+                    if let Some(&index) = instance.param_state().param_map.get(&event.param_id) {
+                        let value =
+                            map_param_in(&instance.param_state().params()[index], event.value);
+
+                        let Some(voice) = voice_from_clap(
+                            &instance.note_input_map,
+                            event.note_id,
+                            event.port_index,
+                            event.channel,
+                            event.key,
+                        ) else {
+                            continue;
+                        };
+
+                        // Values targeting specific voices don't change the parameter's global
+                        // value.
+                        if voice != Voice::default() {
+                            process_state.events.push(Event {
+                                time: event.header.time as i64,
+                                data: Data::VoiceParamChange {
+                                    id: event.param_id,
+                                    voice,
+                                    value,
+                                },
+                            });
+
+                            continue;
+                        }
+
+                        process_state.events.push(Event {
+                            time: event.header.time as i64,
+                            data: Data::ParamChange {
+                                id: event.param_id,
+                                value,
+                            },
+                        });
+
+                        instance.param_state().plugin_params.set(index, value);
+                        instance.param_state().editor_params.set(index, value);
+                        editor_changed = true;
+                    }
+                }
+                CLAP_EVENT_PARAM_MOD => {
+                    let event = &*(event as *const clap_event_param_mod);
+
+                    if let Some(&index) = instance.param_state().param_map.get(&event.param_id) {
+                        let amount =
+                            map_param_mod(&instance.param_state().params()[index], event.amount);
+
+                        let Some(voice) = voice_from_clap(
+                            &instance.note_input_map,
+                            event.note_id,
+                            event.port_index,
+                            event.channel,
+                            event.key,
+                        ) else {
+                            continue;
+                        };
+
+                        process_state.events.push(Event {
+                            time: event.header.time as i64,
+                            data: Data::ParamMod {
+                                id: event.param_id,
+                                voice,
+                                amount,
+                            },
+                        });
+                    }
+                }
+                CLAP_EVENT_NOTE_ON => {
+                    let event = &*(event as *const clap_event_note);
+
+                    let Some(note) = note_from_clap(
+                        &instance.note_input_map,
+                        event.note_id,
+                        event.port_index,
+                        event.channel,
+                        event.key,
+                    ) else {
+                        continue;
+                    };
+
+                    process_state.events.push(Event {
+                        time: event.header.time as i64,
+                        data: Data::NoteOn {
+                            note,
+                            velocity: event.velocity,
+                        },
+                    });
+                }
+                CLAP_EVENT_NOTE_OFF | CLAP_EVENT_NOTE_CHOKE => {
+                    let event = &*(event as *const clap_event_note);
+
+                    let Some(voice) = voice_from_clap(
+                        &instance.note_input_map,
+                        event.note_id,
+                        event.port_index,
+                        event.channel,
+                        event.key,
+                    ) else {
+                        continue;
+                    };
+                    let data = match event.header.type_ {
+                        CLAP_EVENT_NOTE_OFF => Data::NoteOff {
+                            voice,
+                            velocity: event.velocity,
+                        },
+                        _ => Data::Choke { voice },
+                    };
+
+                    process_state.events.push(Event {
+                        time: event.header.time as i64,
+                        data,
+                    });
+                }
+                CLAP_EVENT_NOTE_EXPRESSION => {
+                    let event = &*(event as *const clap_event_note_expression);
+
+                    let Some(expression) = note_expression_from_clap(event.expression_id) else {
+                        continue;
+                    };
+
+                    let Some(voice) = voice_from_clap(
+                        &instance.note_input_map,
+                        event.note_id,
+                        event.port_index,
+                        event.channel,
+                        event.key,
+                    ) else {
+                        continue;
+                    };
+
+                    process_state.events.push(Event {
+                        time: event.header.time as i64,
+                        data: Data::NoteExpression {
+                            voice,
+                            expression,
+                            value: event.value,
+                        },
+                    });
+                }
+                CLAP_EVENT_TRANSPORT => {
+                    let event = &*(event as *const clap_event_transport);
+
+                    process_state.events.push(Event {
+                        time: event.header.time as i64,
+                        data: Data::Transport(transport_from_clap(event)),
+                    });
+                }
+                CLAP_EVENT_MIDI => {
+                    let event = &*(event as *const clap_event_midi);
+
+                    let Some(&port) = instance.note_input_map.get(event.port_index as usize) else {
+                        continue;
+                    };
 
                     process_state.events.push(Event {
                         time: event.header.time as i64,
-                        data: Data::ParamChange {
-                            id: event.param_id,
-                            value,
+                        data: Data::Midi {
+                            port,
+                            bytes: event.data,
                         },
                     });
+                }
+                CLAP_EVENT_MIDI_SYSEX => {
+                    let event = &*(event as *const clap_event_midi_sysex);
 
-                    instance.plugin_params.set(index, value);
+                    let Some(&port) = instance.note_input_map.get(event.port_index as usize) else {
+                        continue;
+                    };
+
+                    process_state.events.push(Event {
+                        time: event.header.time as i64,
+                        data: Data::Sysex {
+                            port,
+                            bytes: slice_from_raw_parts_checked(event.buffer, event.size as usize),
+                        },
+                    });
                 }
+                _ => {}
             }
         }
 
+        let transport = if process.transport.is_null() {
+            Transport::default()
+        } else {
+            transport_from_clap(&*process.transport)
+        };
+
         instance.sync_processor(processor);
-        processor.process(
+        instance.flush_edits(process.out_events, Some(processor));
+        let status = processor.process(
             Buffers::from_raw_parts(
                 RawBuffers {
                     buffers: &process_state.buffer_data,
@@ -324,9 +755,230 @@ impl<P: Plugin> Instance<P> {
                 len,
             ),
             Events::new(&process_state.events),
+            &mut OutputEvents::new(&mut process_state.output_events),
+            &transport,
         );
 
-        CLAP_PROCESS_CONTINUE
+        let out_events = process.out_events;
+        for event in process_state.output_events.sorted() {
+            editor_changed |= matches!(event.data, Data::ParamChange { .. });
+            instance.push_output_event(out_events, event);
+        }
+        process_state.output_events.clear();
+
+        if editor_changed {
+            instance.host.request_callback();
+        }
+
+        // SysEx payloads point into host memory which is only valid for the duration of this call.
+        process_state.events.clear();
+
+        for (index, &bus_index) in instance.output_bus_map.iter().enumerate() {
+            let output = &mut *process.audio_outputs.add(index);
+            output.constant_mask = process_state.buffer_data[bus_index].output_constant.get();
+        }
+
+        match status {
+            Status::Continue => CLAP_PROCESS_CONTINUE,
+            Status::ContinueIfNotQuiet => CLAP_PROCESS_CONTINUE_IF_NOT_QUIET,
+            Status::Tail => CLAP_PROCESS_TAIL,
+            Status::Sleep => CLAP_PROCESS_SLEEP,
+        }
+    }
+
+    unsafe fn flush_edits(
+        &self,
+        out_events: *const clap_output_events,
+        mut processor: Option<&mut Bypass<P::Processor>>,
+    ) {
+        // Never block the audio thread on the editor. Any remaining edits will be flushed later.
+        let Ok(mut edits) = self.host.edits.try_lock() else {
+            return;
+        };
+
+        for edit in edits.drain(..) {
+            match edit {
+                ParamEdit::Begin(id) | ParamEdit::End(id) => {
+                    let type_ = match edit {
+                        ParamEdit::Begin(_) => CLAP_EVENT_PARAM_GESTURE_BEGIN,
+                        _ => CLAP_EVENT_PARAM_GESTURE_END,
+                    };
+
+                    let event = clap_event_param_gesture {
+                        header: event_header::<clap_event_param_gesture>(0, type_),
+                        param_id: id,
+                    };
+                    (*out_events).try_push.unwrap()(out_events, &event.header);
+                }
+                ParamEdit::Set(id, value) => {
+                    let Some(&index) = self.param_state().param_map.get(&id) else {
+                        continue;
+                    };
+
+                    if let Some(processor) = &mut processor {
+                        processor.set_param(id, value);
+                    }
+
+                    // The editor already knows about its own edits, so don't echo them back.
+                    self.param_state().plugin_params.set(index, value);
+                    self.push_param_value(out_events, 0, index, value);
+                }
+            }
+        }
+    }
+
+    unsafe fn push_param_value(
+        &self,
+        out_events: *const clap_output_events,
+        time: i64,
+        index: usize,
+        value: ParamValue,
+    ) {
+        let param = &self.param_state().params()[index];
+
+        let event = clap_event_param_value {
+            header: event_header::<clap_event_param_value>(time, CLAP_EVENT_PARAM_VALUE),
+            param_id: param.id,
+            cookie: ptr::null_mut(),
+            note_id: -1,
+            port_index: -1,
+            channel: -1,
+            key: -1,
+            value: map_param_out(param, value),
+        };
+        (*out_events).try_push.unwrap()(out_events, &event.header);
+    }
+
+    unsafe fn push_output_event(&self, out_events: *const clap_output_events, event: &Event) {
+        let try_push = (*out_events).try_push.unwrap();
+
+        // Events name ports by their index into `PluginInfo::note_ports`, while the host expects
+        // an index among the output ports. Events sent to a port that isn't an output are dropped.
+        let port_to_clap = |port: usize| self.note_output_map.iter().position(|&p| p == port);
+        let voice_port_to_clap = |port: Option<usize>| match port {
+            Some(port) => port_to_clap(port).map(|index| index as isize),
+            None => Some(-1),
+        };
+
+        match event.data {
+            Data::ParamChange { id, value } => {
+                let Some(&index) = self.param_state().param_map.get(&id) else {
+                    return;
+                };
+
+                // Keep the main thread's view of the parameter in sync with the processor's.
+                self.param_state().plugin_params.set(index, value);
+                self.param_state().editor_params.set(index, value);
+
+                self.push_param_value(out_events, event.time, index, value);
+            }
+            Data::NoteOn { note, velocity } => {
+                let Some(port_index) = port_to_clap(note.port) else {
+                    return;
+                };
+
+                let event = clap_event_note {
+                    header: event_header::<clap_event_note>(event.time, CLAP_EVENT_NOTE_ON),
+                    note_id: note.id.unwrap_or(-1),
+                    port_index: port_index as i16,
+                    channel: note.channel as i16,
+                    key: note.key as i16,
+                    velocity,
+                };
+                try_push(out_events, &event.header);
+            }
+            Data::NoteOff { voice, .. } | Data::Choke { voice } => {
+                let (type_, velocity) = match event.data {
+                    Data::NoteOff { velocity, .. } => (CLAP_EVENT_NOTE_OFF, velocity),
+                    _ => (CLAP_EVENT_NOTE_CHOKE, 0.0),
+                };
+
+                let Some(port_index) = voice_port_to_clap(voice.port) else {
+                    return;
+                };
+
+                let event = clap_event_note {
+                    header: event_header::<clap_event_note>(event.time, type_),
+                    note_id: voice.id.unwrap_or(-1),
+                    port_index: port_index as i16,
+                    channel: voice.channel.map_or(-1, |channel| channel as i16),
+                    key: voice.key.map_or(-1, |key| key as i16),
+                    velocity,
+                };
+                try_push(out_events, &event.header);
+            }
+            Data::PolyPressure { note, pressure } => {
+                let Some(port_index) = port_to_clap(note.port) else {
+                    return;
+                };
+
+                let event = clap_event_note_expression {
+                    header: event_header::<clap_event_note_expression>(
+                        event.time,
+                        CLAP_EVENT_NOTE_EXPRESSION,
+                    ),
+                    expression_id: CLAP_NOTE_EXPRESSION_PRESSURE,
+                    note_id: note.id.unwrap_or(-1),
+                    port_index: port_index as i16,
+                    channel: note.channel as i16,
+                    key: note.key as i16,
+                    value: pressure,
+                };
+                try_push(out_events, &event.header);
+            }
+            Data::NoteExpression {
+                voice,
+                expression,
+                value,
+            } => {
+                let Some(port_index) = voice_port_to_clap(voice.port) else {
+                    return;
+                };
+
+                let event = clap_event_note_expression {
+                    header: event_header::<clap_event_note_expression>(
+                        event.time,
+                        CLAP_EVENT_NOTE_EXPRESSION,
+                    ),
+                    expression_id: note_expression_to_clap(expression),
+                    note_id: voice.id.unwrap_or(-1),
+                    port_index: port_index as i16,
+                    channel: voice.channel.map_or(-1, |channel| channel as i16),
+                    key: voice.key.map_or(-1, |key| key as i16),
+                    value,
+                };
+                try_push(out_events, &event.header);
+            }
+            Data::Midi { port, bytes } => {
+                let Some(port_index) = port_to_clap(port) else {
+                    return;
+                };
+
+                let event = clap_event_midi {
+                    header: event_header::<clap_event_midi>(event.time, CLAP_EVENT_MIDI),
+                    port_index: port_index as u16,
+                    data: bytes,
+                };
+                try_push(out_events, &event.header);
+            }
+            Data::Sysex { port, bytes } => {
+                let Some(port_index) = port_to_clap(port) else {
+                    return;
+                };
+
+                let event = clap_event_midi_sysex {
+                    header: event_header::<clap_event_midi_sysex>(
+                        event.time,
+                        CLAP_EVENT_MIDI_SYSEX,
+                    ),
+                    port_index: port_index as u16,
+                    buffer: bytes.as_ptr(),
+                    size: bytes.len() as u32,
+                };
+                try_push(out_events, &event.header);
+            }
+            Data::VoiceParamChange { .. } | Data::ParamMod { .. } | Data::Transport(_) => {}
+        }
     }
 
     unsafe extern "C" fn get_extension(
@@ -343,6 +995,18 @@ impl<P: Plugin> Instance<P> {
             return &Self::AUDIO_PORTS_CONFIG as *const _ as *const c_void;
         }
 
+        if id == CLAP_EXT_LATENCY {
+            return &Self::LATENCY as *const _ as *const c_void;
+        }
+
+        if id == CLAP_EXT_TAIL {
+            return &Self::TAIL as *const _ as *const c_void;
+        }
+
+        if id == CLAP_EXT_NOTE_PORTS {
+            return &Self::NOTE_PORTS as *const _ as *const c_void;
+        }
+
         if id == CLAP_EXT_PARAMS {
             return &Self::PARAMS as *const _ as *const c_void;
         }
@@ -351,6 +1015,15 @@ impl<P: Plugin> Instance<P> {
             return &Self::STATE as *const _ as *const c_void;
         }
 
+        if id == CLAP_EXT_TIMER_SUPPORT {
+            return &Self::TIMER_SUPPORT as *const _ as *const c_void;
+        }
+
+        #[cfg(unix)]
+        if id == CLAP_EXT_POSIX_FD_SUPPORT {
+            return &Self::POSIX_FD_SUPPORT as *const _ as *const c_void;
+        }
+
         if id == CLAP_EXT_GUI {
             let instance = &*(plugin as *const Self);
             if instance.info.has_editor {
@@ -361,7 +1034,79 @@ impl<P: Plugin> Instance<P> {
         ptr::null()
     }
 
-    unsafe extern "C" fn on_main_thread(_plugin: *const clap_plugin) {}
+    unsafe extern "C" fn on_main_thread(plugin: *const clap_plugin) {
+        let instance = &*(plugin as *const Self);
+        let main_thread_state = &mut *instance.main_thread_state.get();
+        let host = &instance.host;
+
+        if host.latency_changed.swap(false, Ordering::Relaxed) {
+            // Latency may only change while the plugin is deactivated.
+            if main_thread_state.config.is_some() {
+                (*host.host).request_restart.unwrap()(host.host);
+            } else if let Some(latency) = host.get_extension::<clap_host_latency>(CLAP_EXT_LATENCY)
+            {
+                latency.changed.unwrap()(host.host);
+            }
+        }
+
+        if host.params.lock().unwrap().is_some() {
+            // The param list may only change while the plugin is deactivated. Hosts usually
+            // reactivate the plugin before the next callback, so the list is applied in deactivate.
+            if main_thread_state.config.is_none() {
+                instance.update_params(main_thread_state);
+            } else if !main_thread_state.params_restart_requested {
+                main_thread_state.params_restart_requested = true;
+                (*host.host).request_restart.unwrap()(host.host);
+            }
+        }
+
+        // If the plugin has been reactivated in the meantime, wait for the next deactivate.
+        if main_thread_state.params_rescan_pending && main_thread_state.config.is_none() {
+            main_thread_state.params_rescan_pending = false;
+            main_thread_state.params_restart_requested = false;
+
+            if let Some(host_params) = host.get_extension::<clap_host_params>(CLAP_EXT_PARAMS) {
+                host_params.rescan.unwrap()(host.host, CLAP_PARAM_RESCAN_ALL);
+            }
+        }
+
+        if host.param_values_changed.swap(false, Ordering::Relaxed) {
+            instance.sync_plugin(&mut main_thread_state.plugin);
+            instance.push_param_values(&main_thread_state.plugin);
+        }
+
+        if host.tail_changed.swap(false, Ordering::Relaxed) {
+            // If the plugin isn't activated, the tail will be recomputed on activation anyway.
+            if let Some(config) = &main_thread_state.config {
+                instance.sync_plugin(&mut main_thread_state.plugin);
+                let tail = main_thread_state.plugin.tail(config);
+                instance.tail.store(tail_to_clap(tail), Ordering::Relaxed);
+
+                // clap_host_tail::changed must be called from the audio thread.
+                instance.tail_changed.store(true, Ordering::Relaxed);
+            }
+        }
+
+        instance.sync_editor(main_thread_state.editor.as_mut());
+    }
+}
+
+impl<P: Plugin> Instance<P> {
+    const LATENCY: clap_plugin_latency = clap_plugin_latency {
+        get: Some(Self::latency_get),
+    };
+
+    unsafe extern "C" fn latency_get(plugin: *const clap_plugin) -> u32 {
+        let instance = &*(plugin as *const Self);
+        let main_thread_state = &mut *instance.main_thread_state.get();
+
+        let Some(config) = &main_thread_state.config else {
+            return 0;
+        };
+
+        instance.sync_plugin(&mut main_thread_state.plugin);
+        main_thread_state.plugin.latency(config) as u32
+    }
 }
 
 impl<P: Plugin> Instance<P> {
@@ -434,6 +1179,67 @@ impl<P: Plugin> Instance<P> {
     }
 }
 
+impl<P: Plugin> Instance<P> {
+    const TAIL: clap_plugin_tail = clap_plugin_tail {
+        get: Some(Self::tail_get),
+    };
+
+    unsafe extern "C" fn tail_get(plugin: *const clap_plugin) -> u32 {
+        let instance = &*(plugin as *const Self);
+
+        instance.tail.load(Ordering::Relaxed)
+    }
+}
+
+impl<P: Plugin> Instance<P> {
+    const NOTE_PORTS: clap_plugin_note_ports = clap_plugin_note_ports {
+        count: Some(Self::note_ports_count),
+        get: Some(Self::note_ports_get),
+    };
+
+    unsafe extern "C" fn note_ports_count(plugin: *const clap_plugin, is_input: bool) -> u32 {
+        let instance = &*(plugin as *const Self);
+
+        if is_input {
+            instance.note_input_map.len() as u32
+        } else {
+            instance.note_output_map.len() as u32
+        }
+    }
+
+    unsafe extern "C" fn note_ports_get(
+        plugin: *const clap_plugin,
+        index: u32,
+        is_input: bool,
+        info: *mut clap_note_port_info,
+    ) -> bool {
+        let instance = &*(plugin as *const Self);
+
+        let port_index = if is_input {
+            instance.note_input_map.get(index as usize)
+        } else {
+            instance.note_output_map.get(index as usize)
+        };
+
+        if let Some(&port_index) = port_index {
+            let port = &instance.info.note_ports[port_index];
+            let port_info = &mut *info;
+
+            port_info.id = index;
+            port_info.supported_dialects = 0;
+            for &dialect in &port.dialects {
+                port_info.supported_dialects |= note_dialect_to_clap(dialect);
+            }
+            port_info.preferred_dialect = note_dialect_to_clap(port.preferred_dialect);
+            copy_cstring(&port.name, &mut port_info.name);
+
+            return true;
+        }
+
+        false
+    }
+}
+
 impl<P: Plugin> Instance<P> {
     const AUDIO_PORTS_CONFIG: clap_plugin_audio_ports_config = clap_plugin_audio_ports_config {
         count: Some(Self::audio_ports_config_count),
@@ -521,7 +1327,7 @@ impl<P: Plugin> Instance<P> {
     unsafe extern "C" fn params_count(plugin: *const clap_plugin) -> u32 {
         let instance = &*(plugin as *const Self);
 
-        instance.info.params.len() as u32
+        instance.param_state().params().len() as u32
     }
 
     unsafe extern "C" fn params_get_info(
@@ -531,22 +1337,53 @@ impl<P: Plugin> Instance<P> {
     ) -> bool {
         let instance = &*(plugin as *const Self);
 
-        if let Some(param) = instance.info.params.get(param_index as usize) {
+        if let Some(param) = instance.param_state().params().get(param_index as usize) {
             let param_info = &mut *param_info;
 
             param_info.id = param.id;
-            param_info.flags = CLAP_PARAM_IS_AUTOMATABLE;
+            param_info.flags = 0;
+            if param.flags.automatable {
+                param_info.flags |= CLAP_PARAM_IS_AUTOMATABLE;
+            }
+            if param.flags.hidden {
+                param_info.flags |= CLAP_PARAM_IS_HIDDEN;
+            }
+            if param.flags.read_only {
+                param_info.flags |= CLAP_PARAM_IS_READONLY;
+            }
+            if param.flags.bypass || instance.info.bypass == Some(param.id) {
+                param_info.flags |= CLAP_PARAM_IS_BYPASS;
+            }
+            if param.flags.list {
+                param_info.flags |= CLAP_PARAM_IS_ENUM;
+            }
+            if param.flags.requires_process {
+                param_info.flags |= CLAP_PARAM_REQUIRES_PROCESS;
+            }
+            if param.flags.modulatable {
+                param_info.flags |= CLAP_PARAM_IS_MODULATABLE;
+            }
+            if param.flags.modulatable_per_note_id {
+                param_info.flags |= CLAP_PARAM_IS_MODULATABLE_PER_NOTE_ID;
+            }
+            if param.flags.modulatable_per_key {
+                param_info.flags |= CLAP_PARAM_IS_MODULATABLE_PER_KEY;
+            }
+            if param.flags.modulatable_per_channel {
+                param_info.flags |= CLAP_PARAM_IS_MODULATABLE_PER_CHANNEL;
+            }
+            if param.flags.modulatable_per_port {
+                param_info.flags |= CLAP_PARAM_IS_MODULATABLE_PER_PORT;
+            }
             param_info.cookie = ptr::null_mut();
             copy_cstring(&param.name, &mut param_info.name);
-            copy_cstring("", &mut param_info.module);
-            if let Some(steps) = param.steps {
+            let module = param.group.as_deref().unwrap_or("");
+            copy_cstring(module, &mut param_info.module);
+            if param.steps.is_some() {
                 param_info.flags |= CLAP_PARAM_IS_STEPPED;
-                param_info.min_value = 0.0;
-                param_info.max_value = (steps.max(2) - 1) as f64;
-            } else {
-                param_info.min_value = 0.0;
-                param_info.max_value = 1.0;
             }
+            param_info.min_value = param.min;
+            param_info.max_value = param.max;
             param_info.default_value = map_param_out(&param, param.default);
 
             return true;
@@ -563,10 +1400,10 @@ impl<P: Plugin> Instance<P> {
         let instance = &*(plugin as *const Self);
         let main_thread_state = &mut *instance.main_thread_state.get();
 
-        if let Some(&index) = instance.param_map.get(&param_id) {
+        if let Some(&index) = instance.param_state().param_map.get(&param_id) {
             instance.sync_plugin(&mut main_thread_state.plugin);
 
-            let param = &instance.info.params[index];
+            let param = &instance.param_state().params()[index];
             *value = map_param_out(param, main_thread_state.plugin.get_param(param_id));
             return true;
         }
@@ -583,8 +1420,8 @@ impl<P: Plugin> Instance<P> {
     ) -> bool {
         let instance = &*(plugin as *const Self);
 
-        if let Some(&index) = instance.param_map.get(&param_id) {
-            let param = &instance.info.params[index];
+        if let Some(&index) = instance.param_state().param_map.get(&param_id) {
+            let param = &instance.param_state().params()[index];
 
             let text = format!("{}", DisplayParam(param, map_param_in(param, value)));
 
@@ -605,9 +1442,9 @@ impl<P: Plugin> Instance<P> {
     ) -> bool {
         let instance = &*(plugin as *const Self);
 
-        if let Some(&index) = instance.param_map.get(&param_id) {
+        if let Some(&index) = instance.param_state().param_map.get(&param_id) {
             if let Ok(text) = CStr::from_ptr(display).to_str() {
-                let param = &instance.info.params[index];
+                let param = &instance.param_state().params()[index];
                 if let Some(out) = (param.parse)(text) {
                     *value = map_param_out(param, out);
                     return true;
@@ -623,7 +1460,7 @@ impl<P: Plugin> Instance<P> {
     unsafe extern "C" fn params_flush(
         plugin: *const clap_plugin,
         in_: *const clap_input_events,
-        _out: *const clap_output_events,
+        out: *const clap_output_events,
     ) {
         let instance = &*(plugin as *const Self);
         let process_state = &mut *instance.process_state.get();
@@ -632,6 +1469,8 @@ impl<P: Plugin> Instance<P> {
         if let Some(processor) = &mut process_state.processor {
             instance.sync_processor(processor);
 
+            let mut editor_changed = false;
+
             let size = (*in_).size.unwrap()(in_);
             for i in 0..size {
                 let event = (*in_).get.unwrap()(in_, i);
@@ -641,13 +1480,22 @@ impl<P: Plugin> Instance<P> {
                 {
                     let event = &*(event as *const clap_event_param_value);
 
-                    if let Some(&index) = instance.param_map.get(&event.param_id) {
-                        let value = map_param_in(&instance.info.params[index], event.value);
+                    if let Some(&index) = instance.param_state().param_map.get(&event.param_id) {
+                        let value =
+                            map_param_in(&instance.param_state().params()[index], event.value);
                         processor.set_param(event.param_id, value);
-                        instance.plugin_params.set(index, value);
+                        instance.param_state().plugin_params.set(index, value);
+                        instance.param_state().editor_params.set(index, value);
+                        editor_changed = true;
                     }
                 }
             }
+
+            instance.flush_edits(out, Some(processor));
+
+            if editor_changed {
+                instance.host.request_callback();
+            }
         }
         // Otherwise, flush will be called on the main thread.
         else {
@@ -664,13 +1512,19 @@ impl<P: Plugin> Instance<P> {
                 {
                     let event = &*(event as *const clap_event_param_value);
 
-                    if let Some(&index) = instance.param_map.get(&event.param_id) {
-                        let value = map_param_in(&instance.info.params[index], event.value);
+                    if let Some(&index) = instance.param_state().param_map.get(&event.param_id) {
+                        let value =
+                            map_param_in(&instance.param_state().params()[index], event.value);
                         main_thread_state.plugin.set_param(event.param_id, value);
-                        instance.processor_params.set(index, value);
+                        instance.param_state().processor_params.set(index, value);
+                        instance.param_state().editor_params.set(index, value);
                     }
                 }
             }
+
+            instance.flush_edits(out, None);
+            instance.sync_plugin(&mut main_thread_state.plugin);
+            instance.sync_editor(main_thread_state.editor.as_mut());
         }
     }
 }
@@ -752,10 +1606,8 @@ impl<P: Plugin> Instance<P> {
 
         instance.sync_plugin(&mut main_thread_state.plugin);
         if let Ok(_) = main_thread_state.plugin.load(&mut StreamReader(stream)) {
-            for (index, param) in instance.info.params.iter().enumerate() {
-                let value = main_thread_state.plugin.get_param(param.id);
-                instance.processor_params.set(index, value);
-            }
+            instance.push_param_values(&main_thread_state.plugin);
+            instance.sync_editor(main_thread_state.editor.as_mut());
 
             return true;
         }
@@ -4,7 +4,11 @@ use clap_sys::{entry::*, version::*};
 
 mod factory;
 mod gui;
+mod host;
 mod instance;
+#[cfg(unix)]
+mod posix_fd;
+mod timer;
 
 #[doc(hidden)]
 pub use factory::Factory;
@@ -0,0 +1,29 @@
+use clap_sys::ext::posix_fd_support::*;
+use clap_sys::plugin::*;
+
+use super::instance::Instance;
+use crate::plugin::{FdFlags, Plugin};
+
+impl<P: Plugin> Instance<P> {
+    pub(super) const POSIX_FD_SUPPORT: clap_plugin_posix_fd_support =
+        clap_plugin_posix_fd_support {
+            on_fd: Some(Self::posix_fd_support_on_fd),
+        };
+
+    unsafe extern "C" fn posix_fd_support_on_fd(
+        plugin: *const clap_plugin,
+        fd: i32,
+        flags: clap_posix_fd_flags,
+    ) {
+        let instance = &*(plugin as *const Self);
+
+        let callback = instance.host.fds.lock().unwrap().get(&fd).cloned();
+        if let Some(callback) = callback {
+            (callback.lock().unwrap())(FdFlags {
+                read: flags & CLAP_POSIX_FD_READ != 0,
+                write: flags & CLAP_POSIX_FD_WRITE != 0,
+                error: flags & CLAP_POSIX_FD_ERROR != 0,
+            });
+        }
+    }
+}
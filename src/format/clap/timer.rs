@@ -0,0 +1,21 @@
+use clap_sys::ext::timer_support::*;
+use clap_sys::id::clap_id;
+use clap_sys::plugin::*;
+
+use super::instance::Instance;
+use crate::plugin::Plugin;
+
+impl<P: Plugin> Instance<P> {
+    pub(super) const TIMER_SUPPORT: clap_plugin_timer_support = clap_plugin_timer_support {
+        on_timer: Some(Self::timer_support_on_timer),
+    };
+
+    unsafe extern "C" fn timer_support_on_timer(plugin: *const clap_plugin, timer_id: clap_id) {
+        let instance = &*(plugin as *const Self);
+
+        let callback = instance.host.timers.lock().unwrap().get(&timer_id).cloned();
+        if let Some(callback) = callback {
+            (callback.lock().unwrap())();
+        }
+    }
+}
@@ -1,2 +1,4 @@
-pub mod clap;
-pub mod vst3;
+pub mod clap;
+pub mod vst3;
+
+mod params;
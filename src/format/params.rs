@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::params::{ParamId, ParamInfo};
+use crate::plugin::PluginInfo;
+use crate::sync::float::AtomicF64;
+use crate::sync::params::ParamValues;
+
+// The param list as currently seen by a format wrapper. This starts out as `PluginInfo::params`
+// and is rebuilt whenever the plugin replaces its params via `Host::set_params`.
+pub struct ParamState {
+    info: Arc<PluginInfo>,
+    replaced: Option<Vec<ParamInfo>>,
+    pub param_map: HashMap<ParamId, usize>,
+    pub plugin_params: ParamValues,
+    pub processor_params: ParamValues,
+    pub editor_params: ParamValues,
+    // The latest value of each param on the main thread. This can be read and updated while the
+    // plugin or editor is borrowed, e.g. when the host calls back into the wrapper from inside one
+    // of their methods.
+    pub values: Vec<AtomicF64>,
+}
+
+impl ParamState {
+    pub fn new(info: &Arc<PluginInfo>, replaced: Option<Vec<ParamInfo>>) -> ParamState {
+        let params = replaced.as_deref().unwrap_or(&info.params);
+
+        let mut param_map = HashMap::new();
+        for (index, param) in params.iter().enumerate() {
+            param_map.insert(param.id, index);
+        }
+
+        ParamState {
+            plugin_params: ParamValues::new(params),
+            processor_params: ParamValues::new(params),
+            editor_params: ParamValues::new(params),
+            values: params.iter().map(|p| AtomicF64::new(p.default)).collect(),
+            info: info.clone(),
+            replaced,
+            param_map,
+        }
+    }
+
+    pub fn params(&self) -> &[ParamInfo] {
+        self.replaced.as_deref().unwrap_or(&self.info.params)
+    }
+}
@@ -1,3 +1,4 @@
+use std::cell::Cell;
 use std::iter::zip;
 use std::ptr::NonNull;
 use std::slice;
@@ -58,6 +59,8 @@ impl ScratchBuffers {
                 buffer_type,
                 start: total_channels,
                 end: total_channels + channel_count,
+                input_constant: 0,
+                output_constant: Cell::new(0),
             });
 
             total_channels += channel_count;
@@ -100,6 +103,11 @@ impl ScratchBuffers {
             return Err(());
         }
 
+        for data in &mut self.data {
+            data.input_constant = 0;
+            data.output_constant.set(0);
+        }
+
         let mut scratch = &mut self.buffers[..];
 
         if len == 0 {
@@ -135,6 +143,16 @@ impl ScratchBuffers {
             }
         }
 
+        // VST3 only reports silent channels, which are a special case of constant channels. Inactive
+        // input buses are fed from the silence buffer.
+        for (input_index, &bus_index) in input_bus_map.iter().enumerate() {
+            self.data[bus_index].input_constant = if self.inputs_active[input_index] {
+                inputs[input_index].silenceFlags
+            } else {
+                u64::MAX
+            };
+        }
+
         // Set up output pointers.
         self.output_ptrs.clear();
         for (output_index, &bus_index) in output_bus_map.iter().enumerate() {
@@ -259,4 +277,30 @@ impl ScratchBuffers {
             len,
         ))
     }
+
+    // VST3 has no notion of constant buffers, so only channels which the processor marked as
+    // constant and which contain zeros are reported as silent.
+    pub unsafe fn set_silence_flags(&self, output_bus_map: &[usize], data: &ProcessData) {
+        let len = data.numSamples as usize;
+        if len == 0 || data.numOutputs as usize != output_bus_map.len() {
+            return;
+        }
+
+        for (output_index, &bus_index) in output_bus_map.iter().enumerate() {
+            if !self.outputs_active[output_index] {
+                continue;
+            }
+
+            let output = &mut *data.outputs.add(output_index);
+            let buffer = &self.data[bus_index];
+            let constant = buffer.output_constant.get();
+
+            output.silenceFlags = 0;
+            for (channel, &ptr) in self.ptrs[buffer.start..buffer.end].iter().enumerate() {
+                if channel < 64 && constant & (1 << channel) != 0 && *ptr == 0.0 {
+                    output.silenceFlags |= 1 << channel;
+                }
+            }
+        }
+    }
 }
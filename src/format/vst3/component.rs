@@ -1,21 +1,25 @@
 use std::cell::UnsafeCell;
 use std::collections::{HashMap, HashSet};
 use std::ffi::{c_void, CStr};
-use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::{mem, ptr};
 
 use vst3::{Class, ComRef, ComWrapper, Steinberg::Vst::*, Steinberg::*};
 
 use super::buffers::ScratchBuffers;
+use super::host::Vst3Host;
 use super::util::{copy_wstring, utf16_from_ptr};
 use super::view::View;
 use crate::bus::{BusDir, Format, Layout};
-use crate::events::{Data, Event, Events};
-use crate::params::ParamId;
-use crate::plugin::{Host, Plugin, PluginInfo};
-use crate::process::{Config, Processor};
-use crate::sync::params::ParamValues;
-use crate::util::{slice_from_raw_parts_checked, DisplayParam};
+use crate::bypass::Bypass;
+use crate::editor::Editor;
+use crate::events::{Data, Event, EventQueue, Events, Note, NoteExpression, OutputEvents, Voice};
+use crate::format::params::ParamState;
+use crate::params::ParamInfo;
+use crate::plugin::{Host, Plugin, PluginInfo, Tail};
+use crate::process::{Config, Processor, Transport};
+use crate::util::{slice_from_raw_parts_checked, sort_events, DisplayParam};
 
 fn format_to_speaker_arrangement(format: &Format) -> SpeakerArrangement {
     match format {
@@ -32,28 +36,193 @@ fn speaker_arrangement_to_format(speaker_arrangement: SpeakerArrangement) -> Opt
     }
 }
 
+fn note_from_vst3(port: usize, note_id: int32, channel: int16, pitch: int16) -> Option<Note> {
+    Some(Note {
+        id: if note_id >= 0 { Some(note_id) } else { None },
+        port,
+        channel: u8::try_from(channel).ok()?,
+        key: u8::try_from(pitch).ok()?,
+    })
+}
+
+fn transport_from_vst3(context: &ProcessContext) -> Transport {
+    use ProcessContext_::StatesAndFlags_::*;
+
+    let has = |flag: ProcessContext_::StatesAndFlags| context.state & flag as uint32 != 0;
+
+    Transport {
+        playing: has(kPlaying),
+        recording: has(kRecording),
+        looping: has(kCycleActive),
+        tempo: has(kTempoValid).then_some(context.tempo),
+        time_signature: has(kTimeSigValid).then_some((
+            context.timeSigNumerator as u16,
+            context.timeSigDenominator as u16,
+        )),
+        pos_beats: has(kProjectTimeMusicValid).then_some(context.projectTimeMusic),
+        // projectTimeSamples is always valid.
+        pos_seconds: (context.sampleRate > 0.0)
+            .then(|| context.projectTimeSamples as f64 / context.sampleRate),
+        bar_start_beats: has(kBarPositionValid).then_some(context.barPositionMusic),
+        loop_range_beats: has(kCycleValid)
+            .then_some((context.cycleStartMusic, context.cycleEndMusic)),
+    }
+}
+
+fn note_expression_from_vst3(type_id: NoteExpressionTypeID) -> Option<NoteExpression> {
+    match type_id {
+        NoteExpressionTypeIDs_::kVolumeTypeID => Some(NoteExpression::Volume),
+        NoteExpressionTypeIDs_::kPanTypeID => Some(NoteExpression::Pan),
+        NoteExpressionTypeIDs_::kTuningTypeID => Some(NoteExpression::Tuning),
+        NoteExpressionTypeIDs_::kVibratoTypeID => Some(NoteExpression::Vibrato),
+        NoteExpressionTypeIDs_::kExpressionTypeID => Some(NoteExpression::Expression),
+        NoteExpressionTypeIDs_::kBrightnessTypeID => Some(NoteExpression::Brightness),
+        _ => None,
+    }
+}
+
+// VST3 has no pressure expression. Per-note pressure is sent as a poly pressure event instead.
+fn note_expression_to_vst3(expression: NoteExpression) -> Option<NoteExpressionTypeID> {
+    match expression {
+        NoteExpression::Volume => Some(NoteExpressionTypeIDs_::kVolumeTypeID),
+        NoteExpression::Pan => Some(NoteExpressionTypeIDs_::kPanTypeID),
+        NoteExpression::Tuning => Some(NoteExpressionTypeIDs_::kTuningTypeID),
+        NoteExpression::Vibrato => Some(NoteExpressionTypeIDs_::kVibratoTypeID),
+        NoteExpression::Expression => Some(NoteExpressionTypeIDs_::kExpressionTypeID),
+        NoteExpression::Brightness => Some(NoteExpressionTypeIDs_::kBrightnessTypeID),
+        NoteExpression::Pressure => None,
+    }
+}
+
+// VST3 note expression values are normalized, whereas NoteExpression values use CLAP's units. A
+// normalized volume of 0.25 corresponds to 0 dB, and normalized tuning covers +/-120 semitones.
+fn note_expression_value_from_vst3(expression: NoteExpression, value: f64) -> f64 {
+    match expression {
+        NoteExpression::Volume => 4.0 * value,
+        NoteExpression::Tuning => 240.0 * (value - 0.5),
+        _ => value,
+    }
+}
+
+fn note_expression_value_to_vst3(expression: NoteExpression, value: f64) -> f64 {
+    match expression {
+        NoteExpression::Volume => value / 4.0,
+        NoteExpression::Tuning => value / 240.0 + 0.5,
+        _ => value,
+    }
+}
+
+struct Unit {
+    name: String,
+    parent: UnitID,
+}
+
+struct Units {
+    units: Vec<Unit>,
+    param_units: Vec<UnitID>,
+}
+
+// Builds the VST3 unit hierarchy from the group path of each param. Unit 0 is the root unit, and
+// every distinct prefix of a group path gets its own unit.
+fn build_units(params: &[ParamInfo]) -> Units {
+    let mut units = vec![Unit {
+        name: "Root".to_string(),
+        parent: kNoParentUnitId,
+    }];
+    let mut unit_map = HashMap::new();
+    let mut param_units = Vec::with_capacity(params.len());
+
+    for param in params {
+        let mut unit = kRootUnitId;
+        let mut path = String::new();
+
+        let names = param.group.iter().flat_map(|group| group.split('/'));
+        for name in names.map(str::trim).filter(|name| !name.is_empty()) {
+            if !path.is_empty() {
+                path.push('/');
+            }
+            path.push_str(name);
+
+            unit = *unit_map.entry(path.clone()).or_insert_with(|| {
+                units.push(Unit {
+                    name: name.to_string(),
+                    parent: unit,
+                });
+                (units.len() - 1) as UnitID
+            });
+        }
+
+        param_units.push(unit);
+    }
+
+    Units { units, param_units }
+}
+
+// Delivers changes recorded while the plugin or editor may have been borrowed further up the
+// stack. Must only be called on the main thread, at a point where the wrapper holds the only
+// reference to the MainThreadState.
+pub fn sync_main_thread<P: Plugin>(
+    host: &Vst3Host,
+    param_state: &ParamState,
+    main_thread_state: &mut MainThreadState<P>,
+) {
+    let plugin = &mut main_thread_state.plugin;
+    for (index, value) in param_state.plugin_params.poll() {
+        plugin.set_param(param_state.params()[index].id, value);
+    }
+
+    // Hosts may already have read the old values in response to `Host::param_values_changed`, so
+    // ask them to read the values again.
+    if host.param_values_changed.swap(false, Ordering::Relaxed) {
+        for (index, param) in param_state.params().iter().enumerate() {
+            let value = plugin.get_param(param.id);
+            param_state.processor_params.set(index, value);
+            param_state.editor_params.set(index, value);
+            param_state.values[index].store(value, Ordering::Relaxed);
+        }
+
+        host.restart_component(RestartFlags_::kParamValuesChanged);
+    }
+
+    let changes = param_state.editor_params.poll();
+    if let Some(editor) = &mut main_thread_state.editor {
+        for (index, value) in changes {
+            editor.set_param(param_state.params()[index].id, value);
+        }
+    }
+}
+
 pub struct MainThreadState<P: Plugin> {
     pub config: Config,
     pub plugin: P,
-    pub editor_params: Vec<f64>,
     pub editor: Option<P::Editor>,
+    pub editor_scale: Option<f64>,
 }
 
 struct ProcessState<P: Plugin> {
     config: Config,
     scratch_buffers: ScratchBuffers,
-    events: Vec<Event>,
-    processor: Option<P::Processor>,
+    events: Vec<Event<'static>>,
+    output_events: EventQueue,
+    processor: Option<Bypass<P::Processor>>,
 }
 
 pub struct Component<P: Plugin> {
+    host: Arc<Vst3Host>,
     info: Arc<PluginInfo>,
     input_bus_map: Vec<usize>,
     output_bus_map: Vec<usize>,
+    note_input_map: Vec<usize>,
+    note_output_map: Vec<usize>,
     layout_set: HashSet<Layout>,
-    param_map: HashMap<ParamId, usize>,
-    plugin_params: ParamValues,
-    processor_params: ParamValues,
+    // The subset of `PluginInfo::note_expressions` which can be expressed as VST3 note expressions.
+    note_expressions: Vec<NoteExpression>,
+    // Kept outside the MainThreadState so that it can be read while the plugin is borrowed.
+    active: AtomicBool,
+    // Only replaced on the main thread while the audio processor is inactive. Shared with views so
+    // that edits made through `EditorContext` can be recorded without borrowing the plugin.
+    param_state: Arc<UnsafeCell<ParamState>>,
+    units: UnsafeCell<Units>,
     main_thread_state: Arc<UnsafeCell<MainThreadState<P>>>,
     // When the audio processor is *not* active, references to ProcessState may only be formed from
     // the main thread. When the audio processor *is* active, references to ProcessState may only
@@ -76,12 +245,29 @@ impl<P: Plugin> Component<P> {
             }
         }
 
+        let mut note_input_map = Vec::new();
+        let mut note_output_map = Vec::new();
+        for (index, port) in info.note_ports.iter().enumerate() {
+            match port.dir {
+                BusDir::In => note_input_map.push(index),
+                BusDir::Out => note_output_map.push(index),
+                BusDir::InOut => {
+                    note_input_map.push(index);
+                    note_output_map.push(index);
+                }
+            }
+        }
+
         let layout_set = info.layouts.iter().cloned().collect::<HashSet<_>>();
 
-        let mut param_map = HashMap::new();
-        for (index, param) in info.params.iter().enumerate() {
-            param_map.insert(param.id, index);
-        }
+        let note_expressions = info
+            .note_expressions
+            .iter()
+            .copied()
+            .filter(|&expression| note_expression_to_vst3(expression).is_some())
+            .collect();
+
+        let units = build_units(&info.params);
 
         let config = Config {
             layout: info.layouts.first().unwrap().clone(),
@@ -89,46 +275,333 @@ impl<P: Plugin> Component<P> {
             max_buffer_size: 0,
         };
 
-        let editor_params = info.params.iter().map(|p| p.default).collect();
-
         let scratch_buffers = ScratchBuffers::new(input_bus_map.len(), output_bus_map.len());
 
+        let host = Arc::new(Vst3Host::new());
+
+        // Only accessed from the main thread, or while the audio thread is known not to be.
+        #[allow(clippy::arc_with_non_send_sync)]
+        let param_state = Arc::new(UnsafeCell::new(ParamState::new(info, None)));
+
+        #[allow(clippy::arc_with_non_send_sync)]
+        let main_thread_state = Arc::new(UnsafeCell::new(MainThreadState {
+            config: config.clone(),
+            plugin: P::new(Host::from_inner(host.clone())),
+            editor: None,
+            editor_scale: None,
+        }));
+
         Component {
+            host,
             info: info.clone(),
             input_bus_map,
             output_bus_map,
+            note_input_map,
+            note_output_map,
             layout_set,
-            param_map,
-            plugin_params: ParamValues::new(&info.params),
-            processor_params: ParamValues::new(&info.params),
-            main_thread_state: Arc::new(UnsafeCell::new(MainThreadState {
-                config: config.clone(),
-                plugin: P::new(Host {}),
-                editor_params,
-                editor: None,
-            })),
+            note_expressions,
+            active: AtomicBool::new(false),
+            param_state,
+            units: UnsafeCell::new(units),
+            main_thread_state,
             process_state: UnsafeCell::new(ProcessState {
                 config,
                 scratch_buffers,
                 events: Vec::with_capacity(4096),
+                output_events: EventQueue::with_capacity(4096, 65536),
                 processor: None,
             }),
         }
     }
 
-    fn sync_plugin(&self, plugin: &mut P) {
-        for (index, value) in self.plugin_params.poll() {
-            let id = self.info.params[index].id;
-            plugin.set_param(id, value);
+    fn param_state(&self) -> &ParamState {
+        unsafe { &*self.param_state.get() }
+    }
+
+    fn units(&self) -> &Units {
+        unsafe { &*self.units.get() }
+    }
+
+    // Applies a param list passed to `Host::set_params`. Must only be called on the main thread
+    // while the audio processor is inactive. Hosts may call into the component while the plugin is
+    // borrowed further up the stack, so this doesn't touch the MainThreadState.
+    unsafe fn update_params(&self) {
+        let Some(params) = self.host.params.lock().unwrap().take() else {
+            return;
+        };
+
+        let old_state = self.param_state();
+        let new_state = ParamState::new(&self.info, Some(params));
+
+        // Carry over any changes which haven't been delivered yet, since the old param indices are
+        // about to become meaningless.
+        let new_index = |index: usize| new_state.param_map.get(&old_state.params()[index].id);
+        for (index, value) in old_state.plugin_params.poll() {
+            if let Some(&index) = new_index(index) {
+                new_state.plugin_params.set(index, value);
+            }
+        }
+        for (index, value) in old_state.editor_params.poll() {
+            if let Some(&index) = new_index(index) {
+                new_state.editor_params.set(index, value);
+            }
         }
+        for (index, value) in old_state.values.iter().enumerate() {
+            if let Some(&index) = new_index(index) {
+                new_state.values[index].store(value.load(Ordering::Relaxed), Ordering::Relaxed);
+            }
+        }
+
+        *self.units.get() = build_units(new_state.params());
+        *self.param_state.get() = new_state;
+
+        // Read the values of any new params from the plugin once it is no longer borrowed.
+        self.host.param_values_changed.store(true, Ordering::Relaxed);
     }
 
-    fn sync_processor(&self, processor: &mut P::Processor) {
-        for (index, value) in self.processor_params.poll() {
-            let id = self.info.params[index].id;
+    fn sync_main_thread(&self, main_thread_state: &mut MainThreadState<P>) {
+        sync_main_thread(&self.host, self.param_state(), main_thread_state);
+    }
+
+    fn sync_processor(&self, processor: &mut Bypass<P::Processor>) {
+        for (index, value) in self.param_state().processor_params.poll() {
+            let id = self.param_state().params()[index].id;
             processor.set_param(id, value);
         }
     }
+
+    unsafe fn push_output_event(&self, data: &ProcessData, event: &Event) {
+        let mut vst3_event = vst3::Steinberg::Vst::Event {
+            busIndex: 0,
+            sampleOffset: event.time as int32,
+            ppqPosition: 0.0,
+            flags: Event_::EventFlags_::kIsLive as uint16,
+            r#type: 0,
+            __field0: mem::zeroed(),
+        };
+
+        let note_on = |note: Note, velocity: f32| NoteOnEvent {
+            channel: note.channel as int16,
+            pitch: note.key as int16,
+            tuning: 0.0,
+            velocity,
+            length: 0,
+            noteId: note.id.unwrap_or(-1),
+        };
+        let note_off = |voice: Voice, velocity: f32| NoteOffEvent {
+            channel: voice.channel.unwrap_or(0) as int16,
+            pitch: voice.key.unwrap_or(0) as int16,
+            velocity,
+            noteId: voice.id.unwrap_or(-1),
+            tuning: 0.0,
+        };
+        let poly_pressure = |note: Note, pressure: f32| PolyPressureEvent {
+            channel: note.channel as int16,
+            pitch: note.key as int16,
+            pressure,
+            noteId: note.id.unwrap_or(-1),
+        };
+        // The host expects an index among the output buses rather than into
+        // `PluginInfo::note_ports`. Events sent to a port that isn't an output are dropped.
+        let port_to_vst3 = |port: usize| {
+            self.note_output_map.iter().position(|&p| p == port).map(|index| index as int32)
+        };
+        let voice_port_to_vst3 = |port: Option<usize>| match port {
+            Some(port) => port_to_vst3(port),
+            None => Some(0),
+        };
+        let midi_cc =
+            |channel: u8, control_number: u8, value: u8, value2: u8| LegacyMIDICCOutEvent {
+                controlNumber: control_number,
+                channel: channel as int8,
+                value: value as int8,
+                value2: value2 as int8,
+            };
+
+        match event.data {
+            Data::ParamChange { id, value } => {
+                let Some(&index) = self.param_state().param_map.get(&id) else {
+                    return;
+                };
+
+                // Keep the main thread's view of the parameter in sync with the processor's.
+                self.param_state().plugin_params.set(index, value);
+
+                let Some(param_changes) = ComRef::from_raw(data.outputParameterChanges) else {
+                    return;
+                };
+
+                let mut queue_index = 0;
+                let queue = param_changes.addParameterData(&id, &mut queue_index);
+                if let Some(queue) = ComRef::from_raw(queue) {
+                    let mut point_index = 0;
+                    queue.addPoint(event.time as int32, value, &mut point_index);
+                }
+
+                return;
+            }
+            Data::NoteOn { note, velocity } => {
+                let Some(bus_index) = port_to_vst3(note.port) else {
+                    return;
+                };
+                vst3_event.busIndex = bus_index;
+                vst3_event.r#type = Event_::EventTypes_::kNoteOnEvent as uint16;
+                vst3_event.__field0.noteOn = note_on(note, velocity as f32);
+            }
+            // VST3 has no dedicated choke event, and its note-offs must identify a single note,
+            // either by ID or by channel and key, so other wildcards can't be expressed.
+            Data::NoteOff { voice, .. } | Data::Choke { voice }
+                if voice.id.is_none() && (voice.channel.is_none() || voice.key.is_none()) =>
+            {
+                return;
+            }
+            Data::NoteOff { voice, velocity } => {
+                let Some(bus_index) = voice_port_to_vst3(voice.port) else {
+                    return;
+                };
+                vst3_event.busIndex = bus_index;
+                vst3_event.r#type = Event_::EventTypes_::kNoteOffEvent as uint16;
+                vst3_event.__field0.noteOff = note_off(voice, velocity as f32);
+            }
+            Data::Choke { voice } => {
+                let Some(bus_index) = voice_port_to_vst3(voice.port) else {
+                    return;
+                };
+                vst3_event.busIndex = bus_index;
+                vst3_event.r#type = Event_::EventTypes_::kNoteOffEvent as uint16;
+                vst3_event.__field0.noteOff = note_off(voice, 0.0);
+            }
+            Data::PolyPressure { note, pressure } => {
+                let Some(bus_index) = port_to_vst3(note.port) else {
+                    return;
+                };
+                vst3_event.busIndex = bus_index;
+                vst3_event.r#type = Event_::EventTypes_::kPolyPressureEvent as uint16;
+                vst3_event.__field0.polyPressure = poly_pressure(note, pressure as f32);
+            }
+            Data::NoteExpression {
+                voice,
+                expression: NoteExpression::Pressure,
+                value,
+            } => {
+                let (Some(channel), Some(key)) = (voice.channel, voice.key) else {
+                    return;
+                };
+
+                let Some(bus_index) = voice_port_to_vst3(voice.port) else {
+                    return;
+                };
+                let note = Note {
+                    id: voice.id,
+                    port: 0,
+                    channel,
+                    key,
+                };
+                vst3_event.busIndex = bus_index;
+                vst3_event.r#type = Event_::EventTypes_::kPolyPressureEvent as uint16;
+                vst3_event.__field0.polyPressure = poly_pressure(note, value as f32);
+            }
+            Data::NoteExpression {
+                voice,
+                expression,
+                value,
+            } => {
+                let (Some(note_id), Some(type_id)) =
+                    (voice.id, note_expression_to_vst3(expression))
+                else {
+                    return;
+                };
+
+                let Some(bus_index) = voice_port_to_vst3(voice.port) else {
+                    return;
+                };
+                vst3_event.busIndex = bus_index;
+                vst3_event.r#type = Event_::EventTypes_::kNoteExpressionValueEvent as uint16;
+                vst3_event.__field0.noteExpressionValue = NoteExpressionValueEvent {
+                    typeId: type_id,
+                    noteId: note_id,
+                    value: note_expression_value_to_vst3(expression, value),
+                };
+            }
+            Data::Midi { port, bytes } => {
+                let Some(bus_index) = port_to_vst3(port) else {
+                    return;
+                };
+                vst3_event.busIndex = bus_index;
+
+                // VST3 has no raw MIDI output, so channel messages are translated into the
+                // corresponding note events or legacy MIDI CC output events.
+                let channel = bytes[0] & 0x0F;
+                let note = Note {
+                    id: None,
+                    port,
+                    channel,
+                    key: bytes[1],
+                };
+                match bytes[0] & 0xF0 {
+                    0x80 => {
+                        vst3_event.r#type = Event_::EventTypes_::kNoteOffEvent as uint16;
+                        vst3_event.__field0.noteOff =
+                            note_off(note.into(), bytes[2] as f32 / 127.0);
+                    }
+                    0x90 => {
+                        vst3_event.r#type = Event_::EventTypes_::kNoteOnEvent as uint16;
+                        vst3_event.__field0.noteOn = note_on(note, bytes[2] as f32 / 127.0);
+                    }
+                    0xA0 => {
+                        vst3_event.r#type = Event_::EventTypes_::kPolyPressureEvent as uint16;
+                        vst3_event.__field0.polyPressure =
+                            poly_pressure(note, bytes[2] as f32 / 127.0);
+                    }
+                    0xB0 => {
+                        vst3_event.r#type = Event_::EventTypes_::kLegacyMIDICCOutEvent as uint16;
+                        vst3_event.__field0.midiCCOut = midi_cc(channel, bytes[1], bytes[2], 0);
+                    }
+                    0xC0 => {
+                        vst3_event.r#type = Event_::EventTypes_::kLegacyMIDICCOutEvent as uint16;
+                        vst3_event.__field0.midiCCOut = midi_cc(
+                            channel,
+                            ControllerNumbers_::kCtrlProgramChange as u8,
+                            bytes[1],
+                            0,
+                        );
+                    }
+                    0xD0 => {
+                        vst3_event.r#type = Event_::EventTypes_::kLegacyMIDICCOutEvent as uint16;
+                        vst3_event.__field0.midiCCOut =
+                            midi_cc(channel, ControllerNumbers_::kAfterTouch as u8, bytes[1], 0);
+                    }
+                    0xE0 => {
+                        vst3_event.r#type = Event_::EventTypes_::kLegacyMIDICCOutEvent as uint16;
+                        vst3_event.__field0.midiCCOut = midi_cc(
+                            channel,
+                            ControllerNumbers_::kPitchBend as u8,
+                            bytes[1],
+                            bytes[2],
+                        );
+                    }
+                    _ => return,
+                }
+            }
+            Data::VoiceParamChange { .. } | Data::ParamMod { .. } | Data::Transport(_) => return,
+            Data::Sysex { port, bytes } => {
+                let Some(bus_index) = port_to_vst3(port) else {
+                    return;
+                };
+                vst3_event.busIndex = bus_index;
+                vst3_event.r#type = Event_::EventTypes_::kDataEvent as uint16;
+                vst3_event.__field0.data = DataEvent {
+                    size: bytes.len() as uint32,
+                    r#type: DataEvent_::DataTypes_::kMidiSysEx as uint32,
+                    bytes: bytes.as_ptr(),
+                };
+            }
+        }
+
+        if let Some(output_events) = ComRef::from_raw(data.outputEvents) {
+            output_events.addEvent(&mut vst3_event);
+        }
+    }
 }
 
 impl<P: Plugin> Class for Component<P> {
@@ -137,6 +610,8 @@ impl<P: Plugin> Class for Component<P> {
         IAudioProcessor,
         IProcessContextRequirements,
         IEditController,
+        INoteExpressionController,
+        IUnitInfo,
     );
 }
 
@@ -166,7 +641,11 @@ impl<P: Plugin> IComponentTrait for Component<P> {
                 BusDirections_::kOutput => self.output_bus_map.len() as int32,
                 _ => 0,
             },
-            MediaTypes_::kEvent => 0,
+            MediaTypes_::kEvent => match dir as BusDirections {
+                BusDirections_::kInput => self.note_input_map.len() as int32,
+                BusDirections_::kOutput => self.note_output_map.len() as int32,
+                _ => 0,
+            },
             _ => 0,
         }
     }
@@ -210,7 +689,31 @@ impl<P: Plugin> IComponentTrait for Component<P> {
                     }
                 }
             }
-            MediaTypes_::kEvent => {}
+            MediaTypes_::kEvent => {
+                let port_index = match dir as BusDirections {
+                    BusDirections_::kInput => self.note_input_map.get(index as usize),
+                    BusDirections_::kOutput => self.note_output_map.get(index as usize),
+                    _ => return kInvalidArgument,
+                };
+
+                if let Some(&port_index) = port_index {
+                    let port = &self.info.note_ports[port_index];
+                    let bus = &mut *bus;
+
+                    bus.mediaType = type_;
+                    bus.direction = dir;
+                    bus.channelCount = 16;
+                    copy_wstring(&port.name, &mut bus.name);
+                    bus.busType = if index == 0 {
+                        BusTypes_::kMain as BusType
+                    } else {
+                        BusTypes_::kAux as BusType
+                    };
+                    bus.flags = BusInfo_::BusFlags_::kDefaultActive as uint32;
+
+                    return kResultOk;
+                }
+            }
             _ => {}
         }
 
@@ -250,7 +753,17 @@ impl<P: Plugin> IComponentTrait for Component<P> {
                 }
                 _ => {}
             },
-            MediaTypes_::kEvent => {}
+            MediaTypes_::kEvent => {
+                let port_index = match dir as BusDirections {
+                    BusDirections_::kInput => self.note_input_map.get(index as usize),
+                    BusDirections_::kOutput => self.note_output_map.get(index as usize),
+                    _ => None,
+                };
+
+                if port_index.is_some() {
+                    return kResultOk;
+                }
+            }
             _ => {}
         }
 
@@ -261,15 +774,19 @@ impl<P: Plugin> IComponentTrait for Component<P> {
         let main_thread_state = &mut *self.main_thread_state.get();
         let process_state = &mut *self.process_state.get();
 
+        self.active.store(state != 0, Ordering::Relaxed);
+
         if state == 0 {
             process_state.processor = None;
+            self.update_params();
         } else {
             let config = main_thread_state.config.clone();
             process_state.config = config.clone();
             process_state.scratch_buffers.resize(&self.info.buses, &config);
 
-            self.sync_plugin(&mut main_thread_state.plugin);
-            process_state.processor = Some(main_thread_state.plugin.processor(config));
+            self.sync_main_thread(main_thread_state);
+            process_state.processor =
+                Some(Bypass::new(&main_thread_state.plugin, &self.info, config));
         }
 
         kResultOk
@@ -298,14 +815,11 @@ impl<P: Plugin> IComponentTrait for Component<P> {
         if let Some(state) = ComRef::from_raw(state) {
             let main_thread_state = &mut *self.main_thread_state.get();
 
-            self.sync_plugin(&mut main_thread_state.plugin);
+            self.sync_main_thread(main_thread_state);
 
             if let Ok(_) = main_thread_state.plugin.load(&mut StreamReader(state)) {
-                for (index, param) in self.info.params.iter().enumerate() {
-                    let value = main_thread_state.plugin.get_param(param.id);
-                    self.processor_params.set(index, value);
-                    main_thread_state.editor_params[index] = value;
-                }
+                self.host.param_values_changed.store(true, Ordering::Relaxed);
+                self.sync_main_thread(main_thread_state);
 
                 return kResultOk;
             }
@@ -341,7 +855,7 @@ impl<P: Plugin> IComponentTrait for Component<P> {
         if let Some(state) = ComRef::from_raw(state) {
             let main_thread_state = &mut *self.main_thread_state.get();
 
-            self.sync_plugin(&mut main_thread_state.plugin);
+            self.sync_main_thread(main_thread_state);
 
             if let Ok(_) = main_thread_state.plugin.save(&mut StreamWriter(state)) {
                 return kResultOk;
@@ -437,7 +951,7 @@ impl<P: Plugin> IAudioProcessorTrait for Component<P> {
     unsafe fn getLatencySamples(&self) -> uint32 {
         let main_thread_state = &mut *self.main_thread_state.get();
 
-        self.sync_plugin(&mut main_thread_state.plugin);
+        self.sync_main_thread(main_thread_state);
         main_thread_state.plugin.latency(&main_thread_state.config) as uint32
     }
 
@@ -497,7 +1011,7 @@ impl<P: Plugin> IAudioProcessorTrait for Component<P> {
                 let id = param_data.getParameterId();
                 let point_count = param_data.getPointCount();
 
-                let Some(&param_index) = self.param_map.get(&id) else {
+                let Some(&param_index) = self.param_state().param_map.get(&id) else {
                     continue;
                 };
 
@@ -515,26 +1029,372 @@ impl<P: Plugin> IAudioProcessorTrait for Component<P> {
                         data: Data::ParamChange { id, value },
                     });
 
-                    self.plugin_params.set(param_index, value);
+                    self.param_state().plugin_params.set(param_index, value);
                 }
             }
         }
 
+        if let Some(input_events) = ComRef::from_raw(data.inputEvents) {
+            for index in 0..input_events.getEventCount() {
+                let mut event = mem::zeroed::<vst3::Steinberg::Vst::Event>();
+                if input_events.getEvent(index, &mut event) != kResultOk {
+                    continue;
+                }
+
+                // Events name ports by their index into `PluginInfo::note_ports`.
+                let Some(&port) = usize::try_from(event.busIndex)
+                    .ok()
+                    .and_then(|bus_index| self.note_input_map.get(bus_index))
+                else {
+                    continue;
+                };
+
+                let data = match event.r#type as Event_::EventTypes {
+                    Event_::EventTypes_::kNoteOnEvent => {
+                        let note_on = &event.__field0.noteOn;
+                        let Some(note) =
+                            note_from_vst3(port, note_on.noteId, note_on.channel, note_on.pitch)
+                        else {
+                            continue;
+                        };
+
+                        Data::NoteOn {
+                            note,
+                            velocity: note_on.velocity as f64,
+                        }
+                    }
+                    Event_::EventTypes_::kNoteOffEvent => {
+                        let note_off = &event.__field0.noteOff;
+                        let Some(note) =
+                            note_from_vst3(port, note_off.noteId, note_off.channel, note_off.pitch)
+                        else {
+                            continue;
+                        };
+
+                        Data::NoteOff {
+                            voice: note.into(),
+                            velocity: note_off.velocity as f64,
+                        }
+                    }
+                    Event_::EventTypes_::kPolyPressureEvent => {
+                        let poly_pressure = &event.__field0.polyPressure;
+                        let Some(note) = note_from_vst3(
+                            port,
+                            poly_pressure.noteId,
+                            poly_pressure.channel,
+                            poly_pressure.pitch,
+                        ) else {
+                            continue;
+                        };
+
+                        Data::PolyPressure {
+                            note,
+                            pressure: poly_pressure.pressure as f64,
+                        }
+                    }
+                    Event_::EventTypes_::kNoteExpressionValueEvent => {
+                        let note_expression = &event.__field0.noteExpressionValue;
+                        let Some(expression) = note_expression_from_vst3(note_expression.typeId)
+                        else {
+                            continue;
+                        };
+
+                        // VST3 note expressions only identify notes by ID.
+                        Data::NoteExpression {
+                            voice: Voice {
+                                id: Some(note_expression.noteId),
+                                port: Some(port),
+                                channel: None,
+                                key: None,
+                            },
+                            expression,
+                            value: note_expression_value_from_vst3(
+                                expression,
+                                note_expression.value,
+                            ),
+                        }
+                    }
+                    Event_::EventTypes_::kDataEvent => {
+                        let data = &event.__field0.data;
+                        if data.r#type as DataEvent_::DataTypes
+                            != DataEvent_::DataTypes_::kMidiSysEx
+                        {
+                            continue;
+                        }
+
+                        Data::Sysex {
+                            port,
+                            bytes: slice_from_raw_parts_checked(data.bytes, data.size as usize),
+                        }
+                    }
+                    _ => continue,
+                };
+
+                process_state.events.push(Event {
+                    time: event.sampleOffset as i64,
+                    data,
+                });
+            }
+        }
+
+        // Parameter changes arrive grouped by parameter and notes arrive separately, so the
+        // combined list has to be put back into time order.
+        sort_events(&mut process_state.events);
+
+        let transport = match data.processContext.as_ref() {
+            Some(context) => transport_from_vst3(context),
+            None => Transport::default(),
+        };
+
         self.sync_processor(processor);
-        processor.process(buffers, Events::new(&process_state.events));
+        // VST3 has no equivalent to CLAP's process status. Silence is reported through the output
+        // silence flags instead.
+        processor.process(
+            buffers,
+            Events::new(&process_state.events),
+            &mut OutputEvents::new(&mut process_state.output_events),
+            &transport,
+        );
+
+        for event in process_state.output_events.sorted() {
+            self.push_output_event(data, event);
+        }
+        process_state.output_events.clear();
+
+        process_state.scratch_buffers.set_silence_flags(&self.output_bus_map, data);
+
+        // SysEx payloads point into host memory which is only valid for the duration of this call.
+        process_state.events.clear();
 
         kResultOk
     }
 
     unsafe fn getTailSamples(&self) -> uint32 {
-        kInfiniteTail
+        let main_thread_state = &mut *self.main_thread_state.get();
+
+        self.sync_main_thread(main_thread_state);
+        match main_thread_state.plugin.tail(&main_thread_state.config) {
+            Tail::None => kNoTail,
+            Tail::Samples(samples) => samples.min(kInfiniteTail as u64 - 1) as uint32,
+            Tail::Infinite => kInfiniteTail,
+        }
     }
 }
 
 impl<P: Plugin> IProcessContextRequirementsTrait for Component<P> {
     unsafe fn getProcessContextRequirements(&self) -> uint32 {
+        use IProcessContextRequirements_::Flags_::*;
+
+        let fields = &self.info.transport;
+
+        let mut flags = 0;
+        if fields.state {
+            flags |= kNeedTransportState;
+        }
+        if fields.tempo {
+            flags |= kNeedTempo;
+        }
+        if fields.time_signature {
+            flags |= kNeedTimeSignature;
+        }
+        if fields.pos_beats {
+            flags |= kNeedProjectTimeMusic;
+        }
+        if fields.bar_start {
+            flags |= kNeedBarPositionMusic;
+        }
+        if fields.loop_range {
+            flags |= kNeedCycleMusic;
+        }
+
+        flags as uint32
+    }
+}
+
+impl<P: Plugin> INoteExpressionControllerTrait for Component<P> {
+    unsafe fn getNoteExpressionCount(&self, _busIndex: int32, _channel: int16) -> int32 {
+        self.note_expressions.len() as int32
+    }
+
+    unsafe fn getNoteExpressionInfo(
+        &self,
+        _busIndex: int32,
+        _channel: int16,
+        noteExpressionIndex: int32,
+        info: *mut NoteExpressionTypeInfo,
+    ) -> tresult {
+        use NoteExpressionTypeInfo_::NoteExpressionTypeFlags_::*;
+
+        let Some(&expression) = usize::try_from(noteExpressionIndex)
+            .ok()
+            .and_then(|index| self.note_expressions.get(index))
+        else {
+            return kInvalidArgument;
+        };
+        let Some(type_id) = note_expression_to_vst3(expression) else {
+            return kInvalidArgument;
+        };
+
+        let (title, units, default, bipolar) = match expression {
+            NoteExpression::Volume => ("Volume", "", 0.25, false),
+            NoteExpression::Pan => ("Pan", "", 0.5, true),
+            NoteExpression::Tuning => ("Tuning", "semitones", 0.5, true),
+            NoteExpression::Vibrato => ("Vibrato", "", 0.0, false),
+            NoteExpression::Expression => ("Expression", "", 0.0, false),
+            NoteExpression::Brightness => ("Brightness", "", 0.5, false),
+            NoteExpression::Pressure => ("Pressure", "", 0.0, false),
+        };
+
+        let info = &mut *info;
+
+        info.typeId = type_id;
+        copy_wstring(title, &mut info.title);
+        copy_wstring(title, &mut info.shortTitle);
+        copy_wstring(units, &mut info.units);
+        info.unitId = -1;
+        info.valueDesc = NoteExpressionValueDescription {
+            defaultValue: default,
+            minimum: 0.0,
+            maximum: 1.0,
+            stepCount: 0,
+        };
+        info.associatedParameterId = kNoParamId;
+        info.flags = if bipolar { kIsBipolar as int32 } else { 0 };
+
+        kResultOk
+    }
+
+    unsafe fn getNoteExpressionStringByValue(
+        &self,
+        _busIndex: int32,
+        _channel: int16,
+        id: NoteExpressionTypeID,
+        valueNormalized: NoteExpressionValue,
+        string: *mut String128,
+    ) -> tresult {
+        let Some(expression) = note_expression_from_vst3(id) else {
+            return kInvalidArgument;
+        };
+
+        let value = note_expression_value_from_vst3(expression, valueNormalized);
+        copy_wstring(&format!("{:.2}", value), &mut *string);
+
+        kResultOk
+    }
+
+    unsafe fn getNoteExpressionValueByString(
+        &self,
+        _busIndex: int32,
+        _channel: int16,
+        id: NoteExpressionTypeID,
+        string: *const TChar,
+        valueNormalized: *mut NoteExpressionValue,
+    ) -> tresult {
+        let Some(expression) = note_expression_from_vst3(id) else {
+            return kInvalidArgument;
+        };
+
+        if let Ok(display) = String::from_utf16(utf16_from_ptr(string)) {
+            if let Ok(value) = display.trim().parse::<f64>() {
+                let value = note_expression_value_to_vst3(expression, value);
+                *valueNormalized = value.clamp(0.0, 1.0);
+                return kResultOk;
+            }
+        }
+
+        kInvalidArgument
+    }
+}
+
+impl<P: Plugin> IUnitInfoTrait for Component<P> {
+    unsafe fn getUnitCount(&self) -> int32 {
+        self.units().units.len() as int32
+    }
+
+    unsafe fn getUnitInfo(&self, unitIndex: int32, info: *mut UnitInfo) -> tresult {
+        if let Some(unit) = self.units().units.get(unitIndex as usize) {
+            let info = &mut *info;
+
+            info.id = unitIndex;
+            info.parentUnitId = unit.parent;
+            copy_wstring(&unit.name, &mut info.name);
+            info.programListId = kNoProgramListId;
+
+            return kResultOk;
+        }
+
+        kInvalidArgument
+    }
+
+    unsafe fn getProgramListCount(&self) -> int32 {
         0
     }
+
+    unsafe fn getProgramListInfo(&self, _listIndex: int32, _info: *mut ProgramListInfo) -> tresult {
+        kInvalidArgument
+    }
+
+    unsafe fn getProgramName(
+        &self,
+        _listId: ProgramListID,
+        _programIndex: int32,
+        _name: *mut String128,
+    ) -> tresult {
+        kInvalidArgument
+    }
+
+    unsafe fn getProgramInfo(
+        &self,
+        _listId: ProgramListID,
+        _programIndex: int32,
+        _attributeId: Vst::CString,
+        _attributeValue: *mut String128,
+    ) -> tresult {
+        kInvalidArgument
+    }
+
+    unsafe fn hasProgramPitchNames(&self, _listId: ProgramListID, _programIndex: int32) -> tresult {
+        kResultFalse
+    }
+
+    unsafe fn getProgramPitchName(
+        &self,
+        _listId: ProgramListID,
+        _programIndex: int32,
+        _midiPitch: int16,
+        _name: *mut String128,
+    ) -> tresult {
+        kInvalidArgument
+    }
+
+    unsafe fn getSelectedUnit(&self) -> UnitID {
+        kRootUnitId
+    }
+
+    unsafe fn selectUnit(&self, _unitId: UnitID) -> tresult {
+        kResultOk
+    }
+
+    unsafe fn getUnitByBus(
+        &self,
+        _type: MediaType,
+        _dir: BusDirection,
+        _busIndex: int32,
+        _channel: int32,
+        unitId: *mut UnitID,
+    ) -> tresult {
+        *unitId = kRootUnitId;
+        kResultOk
+    }
+
+    unsafe fn setUnitProgramData(
+        &self,
+        _listOrUnitId: int32,
+        _programIndex: int32,
+        _data: *mut IBStream,
+    ) -> tresult {
+        kNotImplemented
+    }
 }
 
 impl<P: Plugin> IEditControllerTrait for Component<P> {
@@ -551,25 +1411,44 @@ impl<P: Plugin> IEditControllerTrait for Component<P> {
     }
 
     unsafe fn getParameterCount(&self) -> int32 {
-        self.info.params.len() as int32
+        if !self.active.load(Ordering::Relaxed) {
+            self.update_params();
+        }
+
+        self.param_state().params().len() as int32
     }
 
     unsafe fn getParameterInfo(&self, paramIndex: int32, info: *mut ParameterInfo) -> tresult {
-        if let Some(param) = self.info.params.get(paramIndex as usize) {
+        if let Some(param) = self.param_state().params().get(paramIndex as usize) {
             let info = &mut *info;
 
             info.id = param.id as ParamID;
             copy_wstring(&param.name, &mut info.title);
             copy_wstring(&param.name, &mut info.shortTitle);
-            copy_wstring("", &mut info.units);
+            copy_wstring(&param.unit, &mut info.units);
             info.stepCount = if let Some(steps) = param.steps {
                 (steps.max(2) - 1) as int32
             } else {
                 0
             };
             info.defaultNormalizedValue = param.default;
-            info.unitId = 0;
-            info.flags = ParameterInfo_::ParameterFlags_::kCanAutomate as int32;
+            info.unitId = self.units().param_units[paramIndex as usize];
+            info.flags = 0;
+            if param.flags.automatable {
+                info.flags |= ParameterInfo_::ParameterFlags_::kCanAutomate as int32;
+            }
+            if param.flags.hidden {
+                info.flags |= ParameterInfo_::ParameterFlags_::kIsHidden as int32;
+            }
+            if param.flags.read_only {
+                info.flags |= ParameterInfo_::ParameterFlags_::kIsReadOnly as int32;
+            }
+            if param.flags.bypass || self.info.bypass == Some(param.id) {
+                info.flags |= ParameterInfo_::ParameterFlags_::kIsBypass as int32;
+            }
+            if param.flags.list {
+                info.flags |= ParameterInfo_::ParameterFlags_::kIsList as int32;
+            }
 
             return kResultOk;
         }
@@ -583,8 +1462,8 @@ impl<P: Plugin> IEditControllerTrait for Component<P> {
         valueNormalized: ParamValue,
         string: *mut String128,
     ) -> tresult {
-        if let Some(&index) = self.param_map.get(&id) {
-            let param = &self.info.params[index];
+        if let Some(&index) = self.param_state().param_map.get(&id) {
+            let param = &self.param_state().params()[index];
 
             let display = format!("{}", DisplayParam(param, valueNormalized));
             copy_wstring(&display, &mut *string);
@@ -601,8 +1480,8 @@ impl<P: Plugin> IEditControllerTrait for Component<P> {
         string: *mut TChar,
         valueNormalized: *mut ParamValue,
     ) -> tresult {
-        if let Some(&index) = self.param_map.get(&id) {
-            let param = &self.info.params[index];
+        if let Some(&index) = self.param_state().param_map.get(&id) {
+            let param = &self.param_state().params()[index];
 
             if let Ok(display) = String::from_utf16(utf16_from_ptr(string)) {
                 if let Some(value) = (param.parse)(&display) {
@@ -617,31 +1496,40 @@ impl<P: Plugin> IEditControllerTrait for Component<P> {
 
     unsafe fn normalizedParamToPlain(
         &self,
-        _id: ParamID,
+        id: ParamID,
         valueNormalized: ParamValue,
     ) -> ParamValue {
-        valueNormalized
+        if let Some(&index) = self.param_state().param_map.get(&id) {
+            return (self.param_state().params()[index].to_plain)(valueNormalized);
+        }
+
+        0.0
     }
 
-    unsafe fn plainParamToNormalized(&self, _id: ParamID, plainValue: ParamValue) -> ParamValue {
-        plainValue
+    unsafe fn plainParamToNormalized(&self, id: ParamID, plainValue: ParamValue) -> ParamValue {
+        if let Some(&index) = self.param_state().param_map.get(&id) {
+            return (self.param_state().params()[index].from_plain)(plainValue).clamp(0.0, 1.0);
+        }
+
+        0.0
     }
 
     unsafe fn getParamNormalized(&self, id: ParamID) -> ParamValue {
-        let main_thread_state = &*self.main_thread_state.get();
-
-        if let Some(&index) = self.param_map.get(&id) {
-            return main_thread_state.editor_params[index];
+        if let Some(&index) = self.param_state().param_map.get(&id) {
+            return self.param_state().values[index].load(Ordering::Relaxed);
         }
 
         0.0
     }
 
     unsafe fn setParamNormalized(&self, id: ParamID, value: ParamValue) -> tresult {
-        let main_thread_state = &mut *self.main_thread_state.get();
-
-        if let Some(&index) = self.param_map.get(&id) {
-            main_thread_state.editor_params[index] = value;
+        // Hosts call this to reflect automation and generic UI edits in the controller, so pass
+        // those on to the editor as well. Some hosts call this from inside `performEdit` while the
+        // editor is still borrowed, so the value is delivered the next time the wrapper calls into
+        // the editor.
+        if let Some(&index) = self.param_state().param_map.get(&id) {
+            self.param_state().values[index].store(value, Ordering::Relaxed);
+            self.param_state().editor_params.set(index, value);
 
             return kResultOk;
         }
@@ -649,7 +1537,10 @@ impl<P: Plugin> IEditControllerTrait for Component<P> {
         kInvalidArgument
     }
 
-    unsafe fn setComponentHandler(&self, _handler: *mut IComponentHandler) -> tresult {
+    unsafe fn setComponentHandler(&self, handler: *mut IComponentHandler) -> tresult {
+        let handler = ComRef::from_raw(handler).map(|handler| handler.to_com_ptr());
+        *self.host.handler.lock().unwrap() = handler;
+
         kResultOk
     }
 
@@ -662,7 +1553,13 @@ impl<P: Plugin> IEditControllerTrait for Component<P> {
             return ptr::null_mut();
         }
 
-        let view = ComWrapper::new(View::new(&self.main_thread_state));
-        return view.to_com_ptr::<IPlugView>().unwrap().into_raw();
+        let view = ComWrapper::new(View::new(
+            &self.host,
+            &self.main_thread_state,
+            &self.param_state,
+        ));
+        let ptr = view.to_com_ptr::<IPlugView>().unwrap();
+        view.set_view_ptr(ptr.as_ptr());
+        return ptr.into_raw();
     }
 }
@@ -0,0 +1,130 @@
+#[cfg(unix)]
+use std::os::fd::RawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use vst3::{ComPtr, Steinberg::Vst::*, Steinberg::*};
+
+#[cfg(target_os = "linux")]
+use super::run_loop::RunLoop;
+use crate::params::{ParamId, ParamInfo, ParamValue};
+#[cfg(unix)]
+use crate::plugin::{FdFlags, FdFn};
+use crate::plugin::{HostInner, TimerFn, TimerId};
+
+pub struct Vst3Host {
+    pub handler: Mutex<Option<ComPtr<IComponentHandler>>>,
+    pub params: Mutex<Option<Vec<ParamInfo>>>,
+    // Set when the plugin reports new param values, which the component reads back the next time
+    // it holds the plugin itself.
+    pub param_values_changed: AtomicBool,
+    #[cfg(target_os = "linux")]
+    pub run_loop: Mutex<RunLoop>,
+}
+
+impl Vst3Host {
+    pub fn new() -> Vst3Host {
+        Vst3Host {
+            handler: Mutex::new(None),
+            params: Mutex::new(None),
+            param_values_changed: AtomicBool::new(false),
+            #[cfg(target_os = "linux")]
+            run_loop: Mutex::new(RunLoop::new()),
+        }
+    }
+}
+
+impl Vst3Host {
+    // Clone the handler rather than holding the lock while calling into the host, since the host
+    // may call back into the plugin before returning.
+    fn handler(&self) -> Option<ComPtr<IComponentHandler>> {
+        self.handler.lock().unwrap().clone()
+    }
+
+    pub fn restart_component(&self, flags: RestartFlags) {
+        if let Some(handler) = self.handler() {
+            unsafe { handler.restartComponent(flags as int32) };
+        }
+    }
+
+    pub fn begin_edit(&self, id: ParamId) {
+        if let Some(handler) = self.handler() {
+            unsafe { handler.beginEdit(id) };
+        }
+    }
+
+    pub fn perform_edit(&self, id: ParamId, value: ParamValue) {
+        if let Some(handler) = self.handler() {
+            unsafe { handler.performEdit(id, value) };
+        }
+    }
+
+    pub fn end_edit(&self, id: ParamId) {
+        if let Some(handler) = self.handler() {
+            unsafe { handler.endEdit(id) };
+        }
+    }
+}
+
+// All of these are called on the main thread, as documented on `Host`, so they can call into the
+// component handler directly.
+impl HostInner for Vst3Host {
+    fn latency_changed(&self) {
+        self.restart_component(RestartFlags_::kLatencyChanged);
+    }
+
+    // VST3 has no separate notification for tail changes. Hosts re-query the tail length along
+    // with the latency.
+    fn tail_changed(&self) {
+        self.restart_component(RestartFlags_::kLatencyChanged);
+    }
+
+    // The new params are picked up by the component the next time it is deactivated or the host
+    // queries the param count, whichever comes first.
+    fn set_params(&self, params: Vec<ParamInfo>) {
+        *self.params.lock().unwrap() = Some(params);
+
+        let flags = RestartFlags_::kParamTitlesChanged | RestartFlags_::kReloadComponent;
+        self.restart_component(flags);
+    }
+
+    // The plugin may be borrowed further up the stack (e.g. inside `Plugin::load`), so the values
+    // can't be read back here. The component restarts again once it has read them.
+    fn param_values_changed(&self) {
+        self.param_values_changed.store(true, Ordering::Relaxed);
+        self.restart_component(RestartFlags_::kParamValuesChanged);
+    }
+
+    #[allow(unused_variables)]
+    fn register_timer(&self, period_ms: u32, callback: Box<TimerFn>) -> Option<TimerId> {
+        #[cfg(target_os = "linux")]
+        return self.run_loop.lock().unwrap().register_timer(period_ms, callback);
+
+        #[cfg(not(target_os = "linux"))]
+        return None;
+    }
+
+    #[allow(unused_variables)]
+    fn unregister_timer(&self, id: TimerId) {
+        #[cfg(target_os = "linux")]
+        self.run_loop.lock().unwrap().unregister_timer(id);
+    }
+
+    // The run loop only reports when an fd is readable.
+    #[cfg(unix)]
+    #[allow(unused_variables)]
+    fn register_fd(&self, fd: RawFd, flags: FdFlags, callback: Box<FdFn>) -> bool {
+        #[cfg(target_os = "linux")]
+        return self.run_loop.lock().unwrap().register_fd(fd, callback);
+
+        #[cfg(not(target_os = "linux"))]
+        return false;
+    }
+
+    #[cfg(unix)]
+    #[allow(unused_variables)]
+    fn unregister_fd(&self, fd: RawFd) {
+        #[cfg(target_os = "linux")]
+        self.run_loop.lock().unwrap().unregister_fd(fd);
+    }
+}
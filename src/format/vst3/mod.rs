@@ -7,6 +7,9 @@ use vst3::{ComWrapper, Steinberg::IPluginFactory};
 mod buffers;
 mod component;
 mod factory;
+mod host;
+#[cfg(target_os = "linux")]
+mod run_loop;
 mod util;
 mod view;
 
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::os::fd::RawFd;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use vst3::{Class, ComPtr, ComWrapper, Steinberg::Linux::*, Steinberg::*};
+
+use crate::plugin::{FdFlags, FdFn, TimerFn, TimerId};
+
+// Handlers only hold clones of their callbacks and of the dispatch counter, and don't touch
+// `self` once the callback has been called, since the callback may unregister its own handler.
+struct TimerHandler {
+    callback: Arc<Mutex<Box<TimerFn>>>,
+    dispatching: Arc<AtomicUsize>,
+}
+
+impl Class for TimerHandler {
+    type Interfaces = (ITimerHandler,);
+}
+
+impl ITimerHandlerTrait for TimerHandler {
+    unsafe fn onTimer(&self) {
+        let callback = self.callback.clone();
+        let dispatching = self.dispatching.clone();
+
+        dispatching.fetch_add(1, Ordering::Relaxed);
+        (callback.lock().unwrap())();
+        dispatching.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+struct EventHandler {
+    callback: Arc<Mutex<Box<FdFn>>>,
+    dispatching: Arc<AtomicUsize>,
+}
+
+impl Class for EventHandler {
+    type Interfaces = (IEventHandler,);
+}
+
+impl IEventHandlerTrait for EventHandler {
+    unsafe fn onFDIsSet(&self, _fd: FileDescriptor) {
+        let callback = self.callback.clone();
+        let dispatching = self.dispatching.clone();
+
+        dispatching.fetch_add(1, Ordering::Relaxed);
+        (callback.lock().unwrap())(FdFlags {
+            read: true,
+            ..FdFlags::default()
+        });
+        dispatching.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// Timers and fds registered with the host's run loop, which is obtained from the IPlugFrame and is
+// therefore only available while the plugin's view is open.
+pub struct RunLoop {
+    run_loop: Option<ComPtr<IRunLoop>>,
+    next_timer_id: u32,
+    timers: HashMap<TimerId, ComPtr<ITimerHandler>>,
+    fds: HashMap<RawFd, ComPtr<IEventHandler>>,
+    // Number of callbacks currently running. Unregistered handlers aren't released until this is
+    // zero, so that a callback can't free the handler which is calling it.
+    dispatching: Arc<AtomicUsize>,
+    released_timers: Vec<ComPtr<ITimerHandler>>,
+    released_fds: Vec<ComPtr<IEventHandler>>,
+}
+
+impl RunLoop {
+    pub fn new() -> RunLoop {
+        RunLoop {
+            run_loop: None,
+            next_timer_id: 0,
+            timers: HashMap::new(),
+            fds: HashMap::new(),
+            dispatching: Arc::new(AtomicUsize::new(0)),
+            released_timers: Vec::new(),
+            released_fds: Vec::new(),
+        }
+    }
+
+    fn collect_released(&mut self) {
+        if self.dispatching.load(Ordering::Relaxed) == 0 {
+            self.released_timers.clear();
+            self.released_fds.clear();
+        }
+    }
+
+    // Replaces the host's run loop, unregistering everything from the previous one.
+    pub fn set(&mut self, run_loop: Option<ComPtr<IRunLoop>>) {
+        let ptr = |run_loop: &Option<ComPtr<IRunLoop>>| run_loop.as_ref().map(|r| r.as_ptr());
+        if ptr(&self.run_loop) == ptr(&run_loop) {
+            return;
+        }
+
+        if let Some(run_loop) = &self.run_loop {
+            for (_, handler) in self.timers.drain() {
+                unsafe { run_loop.unregisterTimer(handler.as_ptr()) };
+                self.released_timers.push(handler);
+            }
+
+            for (_, handler) in self.fds.drain() {
+                unsafe { run_loop.unregisterEventHandler(handler.as_ptr()) };
+                self.released_fds.push(handler);
+            }
+        }
+
+        self.run_loop = run_loop;
+
+        self.collect_released();
+    }
+
+    pub fn register_timer(&mut self, period_ms: u32, callback: Box<TimerFn>) -> Option<TimerId> {
+        self.collect_released();
+
+        let run_loop = self.run_loop.as_ref()?;
+
+        let handler = ComWrapper::new(TimerHandler {
+            callback: Arc::new(Mutex::new(callback)),
+            dispatching: self.dispatching.clone(),
+        });
+        let handler = handler.to_com_ptr::<ITimerHandler>().unwrap();
+
+        let result =
+            unsafe { run_loop.registerTimer(handler.as_ptr(), period_ms as TimerInterval) };
+        if result != kResultOk {
+            return None;
+        }
+
+        let id = TimerId(self.next_timer_id);
+        self.next_timer_id = self.next_timer_id.wrapping_add(1);
+        self.timers.insert(id, handler);
+
+        Some(id)
+    }
+
+    pub fn unregister_timer(&mut self, id: TimerId) {
+        if let (Some(run_loop), Some(handler)) = (&self.run_loop, self.timers.remove(&id)) {
+            unsafe { run_loop.unregisterTimer(handler.as_ptr()) };
+            self.released_timers.push(handler);
+        }
+
+        self.collect_released();
+    }
+
+    pub fn register_fd(&mut self, fd: RawFd, callback: Box<FdFn>) -> bool {
+        self.collect_released();
+
+        let Some(run_loop) = &self.run_loop else {
+            return false;
+        };
+
+        if self.fds.contains_key(&fd) {
+            return false;
+        }
+
+        let handler = ComWrapper::new(EventHandler {
+            callback: Arc::new(Mutex::new(callback)),
+            dispatching: self.dispatching.clone(),
+        });
+        let handler = handler.to_com_ptr::<IEventHandler>().unwrap();
+
+        let result = unsafe { run_loop.registerEventHandler(handler.as_ptr(), fd) };
+        if result != kResultOk {
+            return false;
+        }
+
+        self.fds.insert(fd, handler);
+
+        true
+    }
+
+    pub fn unregister_fd(&mut self, fd: RawFd) {
+        if let (Some(run_loop), Some(handler)) = (&self.run_loop, self.fds.remove(&fd)) {
+            unsafe { run_loop.unregisterEventHandler(handler.as_ptr()) };
+            self.released_fds.push(handler);
+        }
+
+        self.collect_released();
+    }
+}
@@ -1,27 +1,195 @@
 use std::cell::UnsafeCell;
 use std::ffi::{c_void, CStr};
-use std::sync::Arc;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::sync::{Arc, Mutex};
+
+use vst3::{Class, ComPtr, ComRef, Steinberg::*};
+
+use super::component::{sync_main_thread, MainThreadState};
+use super::host::Vst3Host;
+use crate::editor::{
+    Editor, EditorContext, EditorContextInner, Key, Modifiers, Parent, RawParent, Size,
+};
+use crate::format::params::ParamState;
+use crate::params::{ParamId, ParamValue};
+use crate::plugin::Plugin;
 
-use vst3::{Class, Steinberg::*};
+// State shared between a view and the context handed to its editor.
+struct ViewShared {
+    host: Arc<Vst3Host>,
+    param_state: Arc<UnsafeCell<ParamState>>,
+    frame: Mutex<Option<ComPtr<IPlugFrame>>>,
+    // Not reference-counted, to avoid a cycle between the view and its editor's context.
+    view: AtomicPtr<IPlugView>,
+    // Set while the editor is requesting a resize, during which the host may call onSize.
+    resizing: AtomicBool,
+}
 
-use super::component::MainThreadState;
-use crate::editor::{Editor, Parent, RawParent};
-use crate::plugin::Plugin;
+impl EditorContextInner for ViewShared {
+    fn begin_gesture(&self, id: ParamId) {
+        self.host.begin_edit(id);
+    }
+
+    // The host forwards the edit to the processor itself, so only the plugin needs to be updated.
+    // The editor is borrowed while this is called, and so may be the plugin, so the value is passed
+    // on to the plugin once the current call into the editor returns.
+    fn set_value(&self, id: ParamId, value: ParamValue) {
+        let param_state = unsafe { &*self.param_state.get() };
+        if let Some(&index) = param_state.param_map.get(&id) {
+            param_state.values[index].store(value, Ordering::Relaxed);
+            param_state.plugin_params.set(index, value);
+        }
+
+        self.host.perform_edit(id, value);
+    }
+
+    fn end_gesture(&self, id: ParamId) {
+        self.host.end_edit(id);
+    }
+
+    fn request_resize(&self, size: Size) -> bool {
+        let frame = self.frame.lock().unwrap().clone();
+        let Some(frame) = frame else {
+            return false;
+        };
+
+        let mut rect = ViewRect {
+            left: 0,
+            top: 0,
+            right: size.width.round() as int32,
+            bottom: size.height.round() as int32,
+        };
+
+        self.resizing.store(true, Ordering::Relaxed);
+        let view = self.view.load(Ordering::Relaxed);
+        let result = unsafe { frame.resizeView(view, &mut rect) };
+        self.resizing.store(false, Ordering::Relaxed);
+
+        result == kResultOk
+    }
+}
+
+fn translate_key(key: char16, key_code: int16) -> Option<Key> {
+    use VirtualKeyCodes_::*;
+
+    let key = match key_code as VirtualKeyCodes {
+        KEY_BACK => Key::Backspace,
+        KEY_TAB => Key::Tab,
+        KEY_CLEAR => Key::Clear,
+        KEY_RETURN => Key::Enter,
+        KEY_PAUSE => Key::Pause,
+        KEY_ESCAPE => Key::Escape,
+        KEY_SPACE => Key::Char(' '),
+        KEY_NEXT => Key::PageDown,
+        KEY_END => Key::End,
+        KEY_HOME => Key::Home,
+        KEY_LEFT => Key::Left,
+        KEY_UP => Key::Up,
+        KEY_RIGHT => Key::Right,
+        KEY_DOWN => Key::Down,
+        KEY_PAGEUP => Key::PageUp,
+        KEY_PAGEDOWN => Key::PageDown,
+        KEY_SELECT => Key::Select,
+        KEY_PRINT => Key::Print,
+        KEY_ENTER => Key::NumpadEnter,
+        KEY_SNAPSHOT => Key::PrintScreen,
+        KEY_INSERT => Key::Insert,
+        KEY_DELETE => Key::Delete,
+        KEY_HELP => Key::Help,
+        code @ KEY_NUMPAD0..=KEY_NUMPAD9 => Key::Numpad((code - KEY_NUMPAD0) as u8),
+        KEY_MULTIPLY => Key::NumpadMultiply,
+        KEY_ADD => Key::NumpadAdd,
+        KEY_SEPARATOR => Key::NumpadSeparator,
+        KEY_SUBTRACT => Key::NumpadSubtract,
+        KEY_DECIMAL => Key::NumpadDecimal,
+        KEY_DIVIDE => Key::NumpadDivide,
+        code @ KEY_F1..=KEY_F12 => Key::F((code - KEY_F1) as u8 + 1),
+        code @ KEY_F13..=KEY_F24 => Key::F((code - KEY_F13) as u8 + 13),
+        KEY_NUMLOCK => Key::NumLock,
+        KEY_SCROLL => Key::ScrollLock,
+        KEY_SHIFT => Key::Shift,
+        KEY_CONTROL => Key::Control,
+        KEY_ALT => Key::Alt,
+        KEY_EQUALS => Key::NumpadEquals,
+        KEY_CONTEXTMENU => Key::ContextMenu,
+        KEY_MEDIA_PLAY => Key::MediaPlay,
+        KEY_MEDIA_STOP => Key::MediaStop,
+        KEY_MEDIA_PREV => Key::MediaPrevious,
+        KEY_MEDIA_NEXT => Key::MediaNext,
+        KEY_VOLUME_UP => Key::VolumeUp,
+        KEY_VOLUME_DOWN => Key::VolumeDown,
+        KEY_SUPER => Key::Super,
+        _ if key != 0 => Key::Char(char::from_u32(key as u32)?),
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+fn translate_modifiers(modifiers: int16) -> Modifiers {
+    use KeyModifier_::*;
+
+    let modifiers = modifiers as KeyModifier;
+    let has = |modifier: KeyModifier| modifiers & modifier != 0;
+
+    // kCommandKey is Command on macOS and Control elsewhere, while kControlKey is Control on
+    // macOS and the Windows key elsewhere.
+    #[cfg(target_os = "macos")]
+    let (control, meta) = (has(kControlKey), has(kCommandKey));
+
+    #[cfg(not(target_os = "macos"))]
+    let (control, meta) = (has(kCommandKey), has(kControlKey));
+
+    Modifiers {
+        shift: has(kShiftKey),
+        control,
+        alt: has(kAlternateKey),
+        meta,
+    }
+}
 
 pub struct View<P: Plugin> {
+    shared: Arc<ViewShared>,
     main_thread_state: Arc<UnsafeCell<MainThreadState<P>>>,
 }
 
 impl<P: Plugin> View<P> {
-    pub fn new(main_thread_state: &Arc<UnsafeCell<MainThreadState<P>>>) -> View<P> {
+    pub fn new(
+        host: &Arc<Vst3Host>,
+        main_thread_state: &Arc<UnsafeCell<MainThreadState<P>>>,
+        param_state: &Arc<UnsafeCell<ParamState>>,
+    ) -> View<P> {
+        // Editors and their contexts are only used on the main thread.
+        #[allow(clippy::arc_with_non_send_sync)]
+        let shared = Arc::new(ViewShared {
+            host: host.clone(),
+            param_state: param_state.clone(),
+            frame: Mutex::new(None),
+            view: AtomicPtr::new(ptr::null_mut()),
+            resizing: AtomicBool::new(false),
+        });
+
         View {
+            shared,
             main_thread_state: main_thread_state.clone(),
         }
     }
+
+    // Delivers changes made during a call into the editor once it has returned.
+    unsafe fn sync(&self, main_thread_state: &mut MainThreadState<P>) {
+        let param_state = &*self.shared.param_state.get();
+        sync_main_thread(&self.shared.host, param_state, main_thread_state);
+    }
+
+    // Must be called with the view's own IPlugView pointer before it is handed to the host.
+    pub fn set_view_ptr(&self, view: *mut IPlugView) {
+        self.shared.view.store(view, Ordering::Relaxed);
+    }
 }
 
 impl<P: Plugin> Class for View<P> {
-    type Interfaces = (IPlugView,);
+    type Interfaces = (IPlugView, IPlugViewContentScaleSupport);
 }
 
 impl<P: Plugin> IPlugViewTrait for View<P> {
@@ -58,11 +226,30 @@ impl<P: Plugin> IPlugViewTrait for View<P> {
         #[cfg(target_os = "linux")]
         let raw_parent = RawParent::X11(parent as std::ffi::c_ulong);
 
+        // The run loop is dropped when the view is removed, and hosts may reattach the view to the
+        // same frame afterwards.
+        #[cfg(target_os = "linux")]
+        {
+            let frame = self.shared.frame.lock().unwrap().clone();
+            let run_loop = frame.and_then(|frame| frame.cast::<Linux::IRunLoop>());
+            self.shared.host.run_loop.lock().unwrap().set(run_loop);
+        }
+
         let main_thread_state = &mut *self.main_thread_state.get();
 
-        let editor = main_thread_state.plugin.editor(Parent::from_raw(raw_parent));
+        let context = EditorContext::from_inner(self.shared.clone());
+        let editor = main_thread_state.plugin.editor(context, Parent::from_raw(raw_parent));
         main_thread_state.editor = Some(editor);
 
+        if let (Some(editor), Some(scale)) = (
+            &mut main_thread_state.editor,
+            main_thread_state.editor_scale,
+        ) {
+            editor.set_scale(scale);
+        }
+
+        self.sync(main_thread_state);
+
         kResultOk
     }
 
@@ -70,20 +257,66 @@ impl<P: Plugin> IPlugViewTrait for View<P> {
         let main_thread_state = &mut *self.main_thread_state.get();
 
         main_thread_state.editor = None;
+        main_thread_state.editor_scale = None;
+
+        self.sync(main_thread_state);
+
+        // Timers and fds can't outlive the frame's run loop.
+        #[cfg(target_os = "linux")]
+        self.shared.host.run_loop.lock().unwrap().set(None);
 
         kResultOk
     }
 
-    unsafe fn onWheel(&self, _distance: f32) -> tresult {
-        kResultFalse
+    unsafe fn onWheel(&self, distance: f32) -> tresult {
+        let main_thread_state = &mut *self.main_thread_state.get();
+
+        let handled = match &mut main_thread_state.editor {
+            Some(editor) => editor.wheel(distance as f64),
+            None => false,
+        };
+
+        self.sync(main_thread_state);
+
+        if handled {
+            kResultTrue
+        } else {
+            kResultFalse
+        }
     }
 
-    unsafe fn onKeyDown(&self, _key: char16, _keyCode: int16, _modifiers: int16) -> tresult {
-        kResultFalse
+    unsafe fn onKeyDown(&self, key: char16, keyCode: int16, modifiers: int16) -> tresult {
+        let main_thread_state = &mut *self.main_thread_state.get();
+
+        let handled = match (&mut main_thread_state.editor, translate_key(key, keyCode)) {
+            (Some(editor), Some(key)) => editor.key_down(key, translate_modifiers(modifiers)),
+            _ => false,
+        };
+
+        self.sync(main_thread_state);
+
+        if handled {
+            kResultTrue
+        } else {
+            kResultFalse
+        }
     }
 
-    unsafe fn onKeyUp(&self, _key: char16, _keyCode: int16, _modifiers: int16) -> tresult {
-        kResultFalse
+    unsafe fn onKeyUp(&self, key: char16, keyCode: int16, modifiers: int16) -> tresult {
+        let main_thread_state = &mut *self.main_thread_state.get();
+
+        let handled = match (&mut main_thread_state.editor, translate_key(key, keyCode)) {
+            (Some(editor), Some(key)) => editor.key_up(key, translate_modifiers(modifiers)),
+            _ => false,
+        };
+
+        self.sync(main_thread_state);
+
+        if handled {
+            kResultTrue
+        } else {
+            kResultFalse
+        }
     }
 
     unsafe fn getSize(&self, size: *mut ViewRect) -> tresult {
@@ -108,23 +341,112 @@ impl<P: Plugin> IPlugViewTrait for View<P> {
         kResultFalse
     }
 
-    unsafe fn onSize(&self, _newSize: *mut ViewRect) -> tresult {
-        kNotImplemented
+    unsafe fn onSize(&self, newSize: *mut ViewRect) -> tresult {
+        if newSize.is_null() {
+            return kResultFalse;
+        }
+
+        // The editor already knows about sizes it requested itself.
+        if self.shared.resizing.load(Ordering::Relaxed) {
+            return kResultOk;
+        }
+
+        let main_thread_state = &mut *self.main_thread_state.get();
+
+        let rect = &*newSize;
+        let size = Size {
+            width: (rect.right - rect.left) as f64,
+            height: (rect.bottom - rect.top) as f64,
+        };
+
+        let accepted = match &mut main_thread_state.editor {
+            Some(editor) => editor.set_size(size),
+            None => false,
+        };
+
+        self.sync(main_thread_state);
+
+        if accepted {
+            kResultOk
+        } else {
+            kResultFalse
+        }
     }
 
-    unsafe fn onFocus(&self, _state: TBool) -> tresult {
-        kResultFalse
+    unsafe fn onFocus(&self, state: TBool) -> tresult {
+        let main_thread_state = &mut *self.main_thread_state.get();
+
+        if let Some(editor) = &mut main_thread_state.editor {
+            editor.focus_changed(state != 0);
+        }
+
+        self.sync(main_thread_state);
+
+        kResultOk
     }
 
-    unsafe fn setFrame(&self, _frame: *mut IPlugFrame) -> tresult {
-        kNotImplemented
+    unsafe fn setFrame(&self, frame: *mut IPlugFrame) -> tresult {
+        let frame = ComRef::from_raw(frame).map(|frame| frame.to_com_ptr());
+
+        #[cfg(target_os = "linux")]
+        {
+            let run_loop = frame.as_ref().and_then(|frame| frame.cast::<Linux::IRunLoop>());
+            self.shared.host.run_loop.lock().unwrap().set(run_loop);
+        }
+
+        *self.shared.frame.lock().unwrap() = frame;
+
+        kResultOk
     }
 
     unsafe fn canResize(&self) -> tresult {
-        kNotImplemented
+        if P::Editor::can_resize() {
+            return kResultTrue;
+        }
+
+        kResultFalse
+    }
+
+    unsafe fn checkSizeConstraint(&self, rect: *mut ViewRect) -> tresult {
+        if rect.is_null() {
+            return kInvalidArgument;
+        }
+
+        let rect = &mut *rect;
+        let size = P::Editor::constrain_size(Size {
+            width: (rect.right - rect.left) as f64,
+            height: (rect.bottom - rect.top) as f64,
+        });
+
+        rect.right = rect.left + size.width.round() as int32;
+        rect.bottom = rect.top + size.height.round() as int32;
+
+        kResultTrue
     }
+}
 
-    unsafe fn checkSizeConstraint(&self, _rect: *mut ViewRect) -> tresult {
-        kNotImplemented
+impl<P: Plugin> IPlugViewContentScaleSupportTrait for View<P> {
+    unsafe fn setContentScaleFactor(
+        &self,
+        factor: IPlugViewContentScaleSupport_::ScaleFactor,
+    ) -> tresult {
+        let main_thread_state = &mut *self.main_thread_state.get();
+
+        // Hosts may set the scale before the view is attached, so hold on to it until then.
+        main_thread_state.editor_scale = Some(factor as f64);
+
+        let supported = if let Some(editor) = &mut main_thread_state.editor {
+            editor.set_scale(factor as f64)
+        } else {
+            P::Editor::supports_scaling()
+        };
+
+        self.sync(main_thread_state);
+
+        if supported {
+            kResultOk
+        } else {
+            kResultFalse
+        }
     }
 }
@@ -7,5 +7,6 @@ pub mod params;
 pub mod plugin;
 pub mod process;
 
+mod bypass;
 mod sync;
 mod util;
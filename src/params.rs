@@ -13,14 +13,59 @@ pub type ParamValue = f64;
 
 pub type ParseFn = dyn Fn(&str) -> Option<ParamValue> + Send + Sync;
 pub type DisplayFn = dyn Fn(ParamValue, &mut Formatter) -> Result<(), fmt::Error> + Send + Sync;
+pub type MapFn = dyn Fn(f64) -> f64 + Send + Sync;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ParamFlags {
+    pub automatable: bool,
+    pub hidden: bool,
+    pub read_only: bool,
+    pub bypass: bool,
+    // The parameter's steps represent a list of discrete choices.
+    pub list: bool,
+    // Changes to the parameter must be delivered to the processor even when no audio is flowing.
+    pub requires_process: bool,
+    pub modulatable: bool,
+    pub modulatable_per_note_id: bool,
+    pub modulatable_per_key: bool,
+    pub modulatable_per_channel: bool,
+    pub modulatable_per_port: bool,
+}
+
+impl Default for ParamFlags {
+    fn default() -> ParamFlags {
+        ParamFlags {
+            automatable: true,
+            hidden: false,
+            read_only: false,
+            bypass: false,
+            list: false,
+            requires_process: false,
+            modulatable: false,
+            modulatable_per_note_id: false,
+            modulatable_per_key: false,
+            modulatable_per_channel: false,
+            modulatable_per_port: false,
+        }
+    }
+}
 
 pub struct ParamInfo {
     pub id: ParamId,
     pub name: String,
     pub default: ParamValue,
     pub steps: Option<u32>,
+    pub flags: ParamFlags,
+    // A '/'-separated path of nested group names, e.g. "Filter/Envelope".
+    pub group: Option<String>,
+    // The range and unit label of the param's plain (non-normalized) value as shown by hosts.
+    pub min: f64,
+    pub max: f64,
+    pub unit: String,
     pub parse: Box<ParseFn>,
     pub display: Box<DisplayFn>,
+    pub to_plain: Box<MapFn>,
+    pub from_plain: Box<MapFn>,
 }
 
 pub trait Params {
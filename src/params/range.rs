@@ -1,15 +1,75 @@
 use super::ParamValue;
 
+// Plain values are the values shown to hosts, e.g. a frequency in Hz or a gain in dB. By default,
+// stepped values use the step index as the plain value, and continuous values use the normalized
+// value.
 pub trait Range<T> {
     fn steps(&self) -> Option<u32>;
     fn encode(&self, value: &T) -> ParamValue;
     fn decode(&self, value: ParamValue) -> T;
+
+    fn min_plain(&self) -> f64 {
+        0.0
+    }
+
+    fn max_plain(&self) -> f64 {
+        if let Some(steps) = self.steps() {
+            (steps.max(2) - 1) as f64
+        } else {
+            1.0
+        }
+    }
+
+    fn decode_plain(&self, value: ParamValue) -> f64 {
+        if let Some(steps) = self.steps() {
+            (value * steps as f64).floor().min(self.max_plain())
+        } else {
+            value
+        }
+    }
+
+    fn encode_plain(&self, plain: f64) -> ParamValue {
+        if let Some(steps) = self.steps() {
+            (plain + 0.5) / steps as f64
+        } else {
+            plain
+        }
+    }
 }
 
+// Uses the same defaults for plain values as `Range`.
 pub trait Encode {
     fn steps() -> Option<u32>;
     fn encode(&self) -> ParamValue;
     fn decode(value: ParamValue) -> Self;
+
+    fn min_plain() -> f64 {
+        0.0
+    }
+
+    fn max_plain() -> f64 {
+        if let Some(steps) = Self::steps() {
+            (steps.max(2) - 1) as f64
+        } else {
+            1.0
+        }
+    }
+
+    fn decode_plain(value: ParamValue) -> f64 {
+        if let Some(steps) = Self::steps() {
+            (value * steps as f64).floor().min(Self::max_plain())
+        } else {
+            value
+        }
+    }
+
+    fn encode_plain(plain: f64) -> ParamValue {
+        if let Some(steps) = Self::steps() {
+            (plain + 0.5) / steps as f64
+        } else {
+            plain
+        }
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -32,6 +92,26 @@ macro_rules! float_range {
             fn decode(&self, value: ParamValue) -> $float {
                 (1.0 - value as $float) * self.start + value as $float * self.end
             }
+
+            #[inline]
+            fn min_plain(&self) -> f64 {
+                self.start as f64
+            }
+
+            #[inline]
+            fn max_plain(&self) -> f64 {
+                self.end as f64
+            }
+
+            #[inline]
+            fn decode_plain(&self, value: ParamValue) -> f64 {
+                self.decode(value) as f64
+            }
+
+            #[inline]
+            fn encode_plain(&self, plain: f64) -> ParamValue {
+                self.encode(&(plain as $float))
+            }
         }
 
         impl Range<$float> for std::ops::RangeInclusive<$float> {
@@ -49,6 +129,26 @@ macro_rules! float_range {
             fn decode(&self, value: ParamValue) -> $float {
                 (1.0 - value as $float) * self.start() + value as $float * self.end()
             }
+
+            #[inline]
+            fn min_plain(&self) -> f64 {
+                *self.start() as f64
+            }
+
+            #[inline]
+            fn max_plain(&self) -> f64 {
+                *self.end() as f64
+            }
+
+            #[inline]
+            fn decode_plain(&self, value: ParamValue) -> f64 {
+                self.decode(value) as f64
+            }
+
+            #[inline]
+            fn encode_plain(&self, plain: f64) -> ParamValue {
+                self.encode(&(plain as $float))
+            }
         }
 
         impl Range<$float> for Log<std::ops::Range<$float>> {
@@ -66,6 +166,26 @@ macro_rules! float_range {
             fn decode(&self, value: ParamValue) -> $float {
                 self.0.start * (self.0.end / self.0.start).powf(value as $float)
             }
+
+            #[inline]
+            fn min_plain(&self) -> f64 {
+                self.0.start as f64
+            }
+
+            #[inline]
+            fn max_plain(&self) -> f64 {
+                self.0.end as f64
+            }
+
+            #[inline]
+            fn decode_plain(&self, value: ParamValue) -> f64 {
+                self.decode(value) as f64
+            }
+
+            #[inline]
+            fn encode_plain(&self, plain: f64) -> ParamValue {
+                self.encode(&(plain as $float))
+            }
         }
 
         impl Range<$float> for Log<std::ops::RangeInclusive<$float>> {
@@ -83,6 +203,26 @@ macro_rules! float_range {
             fn decode(&self, value: ParamValue) -> $float {
                 self.0.start() * (self.0.end() / self.0.start()).powf(value as $float)
             }
+
+            #[inline]
+            fn min_plain(&self) -> f64 {
+                *self.0.start() as f64
+            }
+
+            #[inline]
+            fn max_plain(&self) -> f64 {
+                *self.0.end() as f64
+            }
+
+            #[inline]
+            fn decode_plain(&self, value: ParamValue) -> f64 {
+                self.decode(value) as f64
+            }
+
+            #[inline]
+            fn encode_plain(&self, plain: f64) -> ParamValue {
+                self.encode(&(plain as $float))
+            }
         }
 
         impl Encode for $float {
@@ -123,6 +263,26 @@ macro_rules! int_range {
                 let steps = self.end as f64 - self.start as f64;
                 (self.start as f64 + value * steps) as $int
             }
+
+            #[inline]
+            fn min_plain(&self) -> f64 {
+                self.start as f64
+            }
+
+            #[inline]
+            fn max_plain(&self) -> f64 {
+                self.end as f64 - 1.0
+            }
+
+            #[inline]
+            fn decode_plain(&self, value: ParamValue) -> f64 {
+                (self.decode(value) as f64).clamp(self.min_plain(), self.max_plain())
+            }
+
+            #[inline]
+            fn encode_plain(&self, plain: f64) -> ParamValue {
+                self.encode(&(plain.round() as $int))
+            }
         }
 
         impl Range<$int> for std::ops::RangeInclusive<$int> {
@@ -142,6 +302,26 @@ macro_rules! int_range {
                 let steps = *self.end() as f64 + 1.0 - *self.start() as f64;
                 (*self.start() as f64 + value * steps) as $int
             }
+
+            #[inline]
+            fn min_plain(&self) -> f64 {
+                *self.start() as f64
+            }
+
+            #[inline]
+            fn max_plain(&self) -> f64 {
+                *self.end() as f64
+            }
+
+            #[inline]
+            fn decode_plain(&self, value: ParamValue) -> f64 {
+                (self.decode(value) as f64).clamp(self.min_plain(), self.max_plain())
+            }
+
+            #[inline]
+            fn encode_plain(&self, plain: f64) -> ParamValue {
+                self.encode(&(plain.round() as $int))
+            }
         }
 
         impl Encode for $int {
@@ -1,9 +1,13 @@
 use std::io::{self, Read, Write};
+#[cfg(unix)]
+use std::os::fd::RawFd;
+use std::sync::Arc;
 
-use crate::bus::{BusInfo, Layout};
-use crate::editor::{Editor, Parent};
+use crate::bus::{BusInfo, Layout, NotePortInfo};
+use crate::editor::{Editor, EditorContext, Parent};
+use crate::events::NoteExpression;
 use crate::params::{ParamId, ParamInfo, ParamValue};
-use crate::process::{Config, Processor};
+use crate::process::{Config, Processor, TransportFields};
 
 pub struct PluginInfo {
     pub name: String,
@@ -13,8 +17,12 @@ pub struct PluginInfo {
     pub email: String,
     pub buses: Vec<BusInfo>,
     pub layouts: Vec<Layout>,
+    pub note_ports: Vec<NotePortInfo>,
     pub params: Vec<ParamInfo>,
+    pub bypass: Option<ParamId>,
     pub has_editor: bool,
+    pub transport: TransportFields,
+    pub note_expressions: Vec<NoteExpression>,
 }
 
 impl Default for PluginInfo {
@@ -27,13 +35,123 @@ impl Default for PluginInfo {
             email: String::new(),
             buses: Vec::new(),
             layouts: Vec::new(),
+            note_ports: Vec::new(),
             params: Vec::new(),
+            bypass: None,
             has_editor: false,
+            transport: TransportFields::default(),
+            note_expressions: Vec::new(),
         }
     }
 }
 
-pub struct Host {}
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Tail {
+    None,
+    Samples(u64),
+    Infinite,
+}
+
+pub type TimerFn = dyn FnMut() + Send;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct TimerId(pub(crate) u32);
+
+#[cfg(unix)]
+pub type FdFn = dyn FnMut(FdFlags) + Send;
+
+#[cfg(unix)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct FdFlags {
+    pub read: bool,
+    pub write: bool,
+    pub error: bool,
+}
+
+pub(crate) trait HostInner {
+    fn latency_changed(&self);
+    fn tail_changed(&self);
+    fn set_params(&self, params: Vec<ParamInfo>);
+    fn param_values_changed(&self);
+    fn register_timer(&self, period_ms: u32, callback: Box<TimerFn>) -> Option<TimerId>;
+    fn unregister_timer(&self, id: TimerId);
+    #[cfg(unix)]
+    fn register_fd(&self, fd: RawFd, flags: FdFlags, callback: Box<FdFn>) -> bool;
+    #[cfg(unix)]
+    fn unregister_fd(&self, fd: RawFd);
+}
+
+#[derive(Clone)]
+pub struct Host {
+    inner: Arc<dyn HostInner + Send + Sync>,
+}
+
+impl Host {
+    pub(crate) fn from_inner(inner: Arc<dyn HostInner + Send + Sync>) -> Host {
+        Host { inner }
+    }
+
+    // All of the methods below must be called on the main thread, since VST3 and most CLAP host
+    // callbacks only allow calling back into the host from there. A `Host` may be moved to other
+    // threads, but the processor must not call them.
+    pub fn latency_changed(&self) {
+        self.inner.latency_changed();
+    }
+
+    pub fn tail_changed(&self) {
+        self.inner.tail_changed();
+    }
+
+    // Replaces the plugin's param list. The new list takes effect once the plugin has been
+    // deactivated, so the host may restart the plugin in order to apply it.
+    pub fn set_params(&self, params: Vec<ParamInfo>) {
+        self.inner.set_params(params);
+    }
+
+    // Tells the host that the plugin has changed some of its param values itself, e.g. by loading
+    // a preset. The new values are read back through `Plugin::get_param` once control returns to
+    // the wrapper.
+    pub fn param_values_changed(&self) {
+        self.inner.param_values_changed();
+    }
+
+    // Calls `callback` on the main thread roughly every `period_ms` milliseconds until the timer is
+    // unregistered. Must be called on the main thread, and not from `Plugin::new`. Returns None if
+    // the host doesn't provide timers; on VST3, they are only available on Linux while the plugin's
+    // editor is open. Closing the editor unregisters all of them without calling back into the
+    // plugin, so they must be registered again the next time the editor is opened.
+    pub fn register_timer(
+        &self,
+        period_ms: u32,
+        callback: impl FnMut() + Send + 'static,
+    ) -> Option<TimerId> {
+        self.inner.register_timer(period_ms, Box::new(callback))
+    }
+
+    pub fn unregister_timer(&self, id: TimerId) {
+        self.inner.unregister_timer(id);
+    }
+
+    // Calls `callback` on the main thread whenever `fd` is ready for any of the operations in
+    // `flags`, until the fd is unregistered. Must be called on the main thread, and not from
+    // `Plugin::new`. Returns false if the host doesn't support this or the fd is already
+    // registered. VST3 hosts ignore `flags` and only report readability, and fds are unregistered
+    // along with timers when the editor is closed.
+    #[cfg(unix)]
+    pub fn register_fd(
+        &self,
+        fd: RawFd,
+        flags: FdFlags,
+        callback: impl FnMut(FdFlags) + Send + 'static,
+    ) -> bool {
+        self.inner.register_fd(fd, flags, Box::new(callback))
+    }
+
+    #[cfg(unix)]
+    pub fn unregister_fd(&self, fd: RawFd) {
+        self.inner.unregister_fd(fd);
+    }
+}
 
 pub trait Plugin: Send + Sized + 'static {
     type Processor: Processor;
@@ -46,10 +164,15 @@ pub trait Plugin: Send + Sized + 'static {
     fn save(&self, output: &mut impl Write) -> io::Result<()>;
     fn load(&mut self, input: &mut impl Read) -> io::Result<()>;
     fn processor(&self, config: Config) -> Self::Processor;
-    fn editor(&self, parent: Parent) -> Self::Editor;
+    fn editor(&self, context: EditorContext, parent: Parent) -> Self::Editor;
 
     #[allow(unused_variables)]
     fn latency(&self, config: &Config) -> u64 {
         0
     }
+
+    #[allow(unused_variables)]
+    fn tail(&self, config: &Config) -> Tail {
+        Tail::None
+    }
 }
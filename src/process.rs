@@ -1,6 +1,6 @@
 use crate::buffers::Buffers;
 use crate::bus::Layout;
-use crate::events::Events;
+use crate::events::{Events, OutputEvents};
 use crate::params::{ParamId, ParamValue};
 
 #[derive(Clone)]
@@ -10,8 +10,47 @@ pub struct Config {
     pub max_buffer_size: usize,
 }
 
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Transport {
+    pub playing: bool,
+    pub recording: bool,
+    pub looping: bool,
+    pub tempo: Option<f64>,
+    pub time_signature: Option<(u16, u16)>,
+    pub pos_beats: Option<f64>,
+    pub pos_seconds: Option<f64>,
+    pub bar_start_beats: Option<f64>,
+    pub loop_range_beats: Option<(f64, f64)>,
+}
+
+// Transport fields the processor makes use of. Some hosts (VST3) only provide the fields that are
+// requested up front.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct TransportFields {
+    pub state: bool,
+    pub tempo: bool,
+    pub time_signature: bool,
+    pub pos_beats: bool,
+    pub bar_start: bool,
+    pub loop_range: bool,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Status {
+    Continue,
+    ContinueIfNotQuiet,
+    Tail,
+    Sleep,
+}
+
 pub trait Processor: Send + Sized + 'static {
     fn set_param(&mut self, id: ParamId, value: ParamValue);
     fn reset(&mut self);
-    fn process(&mut self, buffers: Buffers, events: Events);
+    fn process(
+        &mut self,
+        buffers: Buffers,
+        events: Events,
+        output: &mut OutputEvents,
+        transport: &Transport,
+    ) -> Status;
 }
@@ -3,6 +3,7 @@ use std::fmt::{self, Display, Formatter};
 use std::os::raw::c_char;
 use std::slice;
 
+use crate::events::Event;
 use crate::params::{ParamInfo, ParamValue};
 
 pub fn copy_cstring(src: &str, dst: &mut [c_char]) {
@@ -32,6 +33,12 @@ pub unsafe fn slice_from_raw_parts_checked<'a, T>(ptr: *const T, len: usize) ->
     }
 }
 
+// Sorts events by timestamp. The sort is stable, so events with the same timestamp keep the order
+// in which the host sent them.
+pub fn sort_events(events: &mut [Event]) {
+    events.sort_by_key(|event| event.time);
+}
+
 pub struct DisplayParam<'a>(pub &'a ParamInfo, pub ParamValue);
 
 impl<'a> Display for DisplayParam<'a> {
@@ -39,3 +46,32 @@ impl<'a> Display for DisplayParam<'a> {
         (self.0.display)(self.1, f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Data;
+
+    #[test]
+    fn sort_events_stable() {
+        let event = |time, id| Event {
+            time,
+            data: Data::ParamChange { id, value: 0.0 },
+        };
+
+        let mut events = [
+            event(3, 0),
+            event(1, 1),
+            event(3, 2),
+            event(0, 3),
+            event(1, 4),
+        ];
+        sort_events(&mut events);
+
+        let order = events.map(|event| match event.data {
+            Data::ParamChange { id, .. } => Some(id),
+            _ => None,
+        });
+        assert_eq!(order, [Some(3), Some(1), Some(4), Some(0), Some(2)]);
+    }
+}